@@ -1,4 +1,5 @@
 #![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
+use chrono::{DateTime, Utc};
 use poem::{Error, IntoResponse};
 
 use serde::{Deserialize, Serialize};
@@ -32,11 +33,65 @@ pub enum SigmaApiResponse<T: Send + Sync + ToJSON + ParseFromJSON, E: Send + ToJ
 
 pub struct SigmaApiData<T: Send + Sync + ToJSON + ParseFromJSON> {
     data: T,
+    /// Links to other pages of paginated data
+    #[oai(skip_serializing_if_is_none)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    links: Option<SigmaApiLinks>,
+    /// Date range the data was searched in
+    #[oai(skip_serializing_if_is_none)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    range: Option<SigmaApiRange>,
 }
 
 impl<T: Send + Sync + ToJSON + ParseFromJSON> SigmaApiData<T> {
     pub fn new(data: T) -> Self {
-        Self { data }
+        Self {
+            data,
+            links: None,
+            range: None,
+        }
+    }
+    pub fn with_links(mut self, links: SigmaApiLinks) -> Self {
+        self.links = Some(links);
+        self
+    }
+    pub fn with_range(mut self, range: SigmaApiRange) -> Self {
+        self.range = Some(range);
+        self
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Object)]
+
+pub struct SigmaApiLinks {
+    /// Next page, missing on the last one
+    #[oai(skip_serializing_if_is_none)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    next: Option<String>,
+}
+
+impl SigmaApiLinks {
+    pub fn new(next: Option<String>) -> Self {
+        Self { next }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Object)]
+
+pub struct SigmaApiRange {
+    /// Beginning of the range, missing when unbounded
+    #[oai(skip_serializing_if_is_none)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    date_from: Option<DateTime<Utc>>,
+    /// End of the range, missing when unbounded
+    #[oai(skip_serializing_if_is_none)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    date_to: Option<DateTime<Utc>>,
+}
+
+impl SigmaApiRange {
+    pub fn new(date_from: Option<DateTime<Utc>>, date_to: Option<DateTime<Utc>>) -> Self {
+        Self { date_from, date_to }
     }
 }
 
@@ -0,0 +1,1001 @@
+#![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use futures::stream::TryStreamExt;
+use mongodb::{bson::doc, error::Result as MongoResult, Collection, Cursor};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use poem::{
+    handler,
+    http::{header, StatusCode},
+    web::{Data, Redirect},
+    Body, IntoResponse, Request, Response, Route,
+};
+use quick_xml::{
+    escape::escape,
+    events::{BytesStart, Event},
+    name::ResolveResult,
+    NsReader,
+};
+use timetable::{
+    filter::TimetableFilter,
+    group::GroupCode,
+    ics::{calendar_ctag, entries_calendar, etags_ctag, event_calendar, event_etag},
+    person::Person,
+    timetable::TimeTableEntry,
+};
+use tracing::error;
+
+use crate::query::{find_filtered, find_groups, find_tutors};
+
+/// Path the CalDAV server is nested under
+pub(crate) const CALDAV_ROOT: &str = "/caldav";
+
+const DAV: &str = "DAV:";
+const CALDAV: &str = "urn:ietf:params:xml:ns:caldav";
+const CALENDARSERVER: &str = "http://calendarserver.org/ns/";
+
+/// Characters escaped in path segments of hrefs
+const SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// Read-only CalDAV server with one calendar collection per group and per tutor:
+///
+/// - `/` - principal, its calendar homes are `/groups/` and `/tutors/`
+/// - `/groups/{group}/` and `/tutors/{tutor id}/` - calendar collections
+/// - `/groups/{group}/{entry id}.ics` - calendar object resource of a single entry
+pub(crate) fn caldav() -> Route {
+    Route::new().at("/", dav).at("/*path", dav)
+}
+
+/// RFC 6764 bootstrapping, clients given only the host look for the server here
+#[handler]
+pub(crate) fn well_known_caldav() -> Redirect {
+    Redirect::moved_permanent(format!("{CALDAV_ROOT}/"))
+}
+
+/// Calendar homes, one per kind of owner of a calendar
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Home {
+    Groups,
+    Tutors,
+}
+
+impl Home {
+    fn segment(self) -> &'static str {
+        match self {
+            Self::Groups => "groups",
+            Self::Tutors => "tutors",
+        }
+    }
+    fn display_name(self) -> &'static str {
+        match self {
+            Self::Groups => "Grupy",
+            Self::Tutors => "Prowadzący",
+        }
+    }
+}
+
+/// Resource addressed by a request path
+enum Resource {
+    Principal,
+    Home(Home),
+    Calendar(Home, String),
+    Event(Home, String, String),
+}
+
+impl Resource {
+    fn parse(path: &str) -> Option<Self> {
+        let segments = path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(|segment| {
+                percent_decode_str(segment)
+                    .decode_utf8()
+                    .ok()
+                    .map(|segment| segment.into_owned())
+            })
+            .collect::<Option<Vec<String>>>()?;
+        let home = |segment: &str| match segment {
+            "groups" => Some(Home::Groups),
+            "tutors" => Some(Home::Tutors),
+            _ => None,
+        };
+        match segments.as_slice() {
+            [] => Some(Self::Principal),
+            [segment] => Some(Self::Home(home(segment)?)),
+            [segment, name] => Some(Self::Calendar(home(segment)?, name.clone())),
+            [segment, name, file] => Some(Self::Event(
+                home(segment)?,
+                name.clone(),
+                file.strip_suffix(".ics")?.to_string(),
+            )),
+            _ => None,
+        }
+    }
+}
+
+fn principal_href() -> String {
+    format!("{CALDAV_ROOT}/")
+}
+
+fn home_href(home: Home) -> String {
+    format!("{CALDAV_ROOT}/{}/", home.segment())
+}
+
+fn calendar_href(home: Home, name: &str) -> String {
+    format!(
+        "{CALDAV_ROOT}/{}/{}/",
+        home.segment(),
+        utf8_percent_encode(name, SEGMENT)
+    )
+}
+
+fn event_href(home: Home, name: &str, entry: &TimeTableEntry) -> String {
+    format!(
+        "{}{}.ics",
+        calendar_href(home, name),
+        utf8_percent_encode(entry.get_id(), SEGMENT)
+    )
+}
+
+/// Name of a property, qualified by its namespace
+#[derive(Clone, PartialEq, Eq)]
+struct PropName {
+    namespace: String,
+    name: String,
+}
+
+impl PropName {
+    fn is(&self, namespace: &str, name: &str) -> bool {
+        self.namespace == namespace && self.name == name
+    }
+    /// Opening and closing tag, known namespaces use the prefixes declared by `Multistatus`
+    fn tags(&self) -> (String, String) {
+        let prefix = match self.namespace.as_str() {
+            DAV => Some("D"),
+            CALDAV => Some("C"),
+            CALENDARSERVER => Some("CS"),
+            _ => None,
+        };
+        match prefix {
+            Some(prefix) => (
+                format!("<{prefix}:{}>", self.name),
+                format!("</{prefix}:{}>", self.name),
+            ),
+            None => (
+                format!("<{} xmlns=\"{}\">", self.name, escape(&self.namespace)),
+                format!("</{}>", self.name),
+            ),
+        }
+    }
+}
+
+/// Body of a `PROPFIND` or `REPORT` request
+#[derive(Default)]
+struct DavRequest {
+    /// Local name of the root element, e.g. `calendar-query`
+    root: String,
+    /// Requested properties, `None` for `allprop`
+    props: Option<Vec<PropName>>,
+    /// Resources requested by `calendar-multiget`
+    hrefs: Vec<String>,
+    /// Components requested by the filter of `calendar-query`
+    components: Vec<String>,
+    /// Beginning of the time range of `calendar-query`
+    start: Option<DateTime<Utc>>,
+    /// End of the time range of `calendar-query`
+    end: Option<DateTime<Utc>>,
+}
+
+impl DavRequest {
+    /// Parses the body, an empty one is an `allprop` request
+    fn parse(body: &str) -> Option<Self> {
+        let mut request = Self::default();
+        let mut reader = NsReader::from_str(body);
+        reader.trim_text(true);
+        let mut stack: Vec<PropName> = vec![];
+        loop {
+            match reader.read_resolved_event().ok()? {
+                (namespace, Event::Start(element)) => {
+                    let name = request.element(namespace, &element, &stack)?;
+                    stack.push(name);
+                }
+                (namespace, Event::Empty(element)) => {
+                    request.element(namespace, &element, &stack)?;
+                }
+                (_, Event::End(_)) => {
+                    stack.pop();
+                }
+                (_, Event::Text(text)) if stack.last().is_some_and(|name| name.is(DAV, "href")) => {
+                    request.hrefs.push(text.unescape().ok()?.into_owned());
+                }
+                (_, Event::Eof) => break,
+                _ => {}
+            }
+        }
+        Some(request)
+    }
+
+    fn element(
+        &mut self,
+        namespace: ResolveResult,
+        element: &BytesStart,
+        stack: &[PropName],
+    ) -> Option<PropName> {
+        let name = PropName {
+            namespace: match namespace {
+                ResolveResult::Bound(namespace) => {
+                    String::from_utf8(namespace.into_inner().to_vec()).ok()?
+                }
+                _ => String::new(),
+            },
+            name: String::from_utf8(element.local_name().into_inner().to_vec()).ok()?,
+        };
+        let attribute = |key: &[u8]| {
+            element
+                .attributes()
+                .flatten()
+                .find(|attribute| attribute.key.local_name().into_inner() == key)
+                .and_then(|attribute| attribute.unescape_value().ok())
+                .map(|value| value.into_owned())
+        };
+        match stack.last() {
+            None => self.root = name.name.clone(),
+            Some(parent) if parent.is(DAV, "prop") => {
+                self.props.get_or_insert_with(Vec::new).push(name.clone());
+            }
+            _ => {}
+        }
+        if name.is(DAV, "prop") {
+            self.props.get_or_insert_with(Vec::new);
+        } else if name.is(CALDAV, "comp-filter") {
+            self.components.extend(attribute(b"name"));
+        } else if name.is(CALDAV, "time-range") {
+            self.start = attribute(b"start").and_then(|start| parse_datetime(&start));
+            self.end = attribute(b"end").and_then(|end| parse_datetime(&end));
+        }
+        Some(name)
+    }
+
+    fn wants(&self, namespace: &str, name: &str) -> bool {
+        match &self.props {
+            Some(props) => props.iter().any(|prop| prop.is(namespace, name)),
+            None => !(namespace == CALDAV && name == "calendar-data"),
+        }
+    }
+}
+
+/// Date and time in the UTC form of RFC 5545, e.g. `20221018T070000Z`
+fn parse_datetime(value: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ")
+        .ok()
+        .map(|datetime| Utc.from_utc_datetime(&datetime))
+}
+
+/// `207 Multi-Status` response body
+struct Multistatus {
+    body: String,
+}
+
+impl Multistatus {
+    fn new() -> Self {
+        Self {
+            body: format!(
+                "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:multistatus xmlns:D=\"{DAV}\" xmlns:C=\"{CALDAV}\" xmlns:CS=\"{CALENDARSERVER}\">"
+            ),
+        }
+    }
+
+    /// Adds a response with the requested properties of a resource, `props` are its values as XML
+    fn response(&mut self, href: &str, request: &DavRequest, props: Vec<(PropName, String)>) {
+        let (found, missing): (Vec<(PropName, String)>, Vec<PropName>) = match &request.props {
+            Some(requested) => {
+                let mut found = vec![];
+                let mut missing = vec![];
+                for name in requested {
+                    match props.iter().find(|(prop, _)| prop == name) {
+                        Some(prop) => found.push(prop.clone()),
+                        None => missing.push(name.clone()),
+                    }
+                }
+                (found, missing)
+            }
+            None => (
+                props
+                    .into_iter()
+                    .filter(|(prop, _)| request.wants(&prop.namespace, &prop.name))
+                    .collect(),
+                vec![],
+            ),
+        };
+        self.body
+            .push_str(&format!("<D:response><D:href>{}</D:href>", escape(href)));
+        if !found.is_empty() {
+            self.body.push_str("<D:propstat><D:prop>");
+            for (name, value) in found {
+                let (open, close) = name.tags();
+                self.body.push_str(&format!("{open}{value}{close}"));
+            }
+            self.body
+                .push_str("</D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat>");
+        }
+        if !missing.is_empty() {
+            self.body.push_str("<D:propstat><D:prop>");
+            for name in missing {
+                let (open, close) = name.tags();
+                self.body.push_str(&format!("{open}{close}"));
+            }
+            self.body
+                .push_str("</D:prop><D:status>HTTP/1.1 404 Not Found</D:status></D:propstat>");
+        }
+        self.body.push_str("</D:response>");
+    }
+
+    /// Adds a response for a resource which doesn't exist
+    fn not_found(&mut self, href: &str) {
+        self.body.push_str(&format!(
+            "<D:response><D:href>{}</D:href><D:status>HTTP/1.1 404 Not Found</D:status></D:response>",
+            escape(href)
+        ));
+    }
+
+    fn finish(mut self) -> Response {
+        self.body.push_str("</D:multistatus>");
+        Response::builder()
+            .status(StatusCode::MULTI_STATUS)
+            .content_type("application/xml; charset=utf-8")
+            .body(self.body)
+    }
+}
+
+fn prop(namespace: &str, name: &str, value: String) -> (PropName, String) {
+    (
+        PropName {
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+        },
+        value,
+    )
+}
+
+/// Properties every resource has
+fn common_props() -> Vec<(PropName, String)> {
+    vec![
+        prop(
+            DAV,
+            "current-user-principal",
+            format!("<D:href>{}</D:href>", principal_href()),
+        ),
+        prop(
+            DAV,
+            "current-user-privilege-set",
+            "<D:privilege><D:read/></D:privilege>".to_string(),
+        ),
+    ]
+}
+
+fn principal_props() -> Vec<(PropName, String)> {
+    let mut props = common_props();
+    props.extend([
+        prop(
+            DAV,
+            "resourcetype",
+            "<D:collection/><D:principal/>".to_string(),
+        ),
+        prop(DAV, "displayname", "PJATK".to_string()),
+        prop(
+            DAV,
+            "principal-URL",
+            format!("<D:href>{}</D:href>", principal_href()),
+        ),
+        prop(
+            CALDAV,
+            "calendar-home-set",
+            [Home::Groups, Home::Tutors]
+                .map(|home| format!("<D:href>{}</D:href>", home_href(home)))
+                .concat(),
+        ),
+    ]);
+    props
+}
+
+fn home_props(home: Home) -> Vec<(PropName, String)> {
+    let mut props = common_props();
+    props.extend([
+        prop(DAV, "resourcetype", "<D:collection/>".to_string()),
+        prop(DAV, "displayname", home.display_name().to_string()),
+    ]);
+    props
+}
+
+/// `ctag` is only given when asked for, as it takes hashing every entry of the calendar
+fn calendar_props(display_name: &str, ctag: Option<String>) -> Vec<(PropName, String)> {
+    let mut props = common_props();
+    props.extend([
+        prop(
+            DAV,
+            "resourcetype",
+            "<D:collection/><C:calendar/>".to_string(),
+        ),
+        prop(DAV, "displayname", escape(display_name).into_owned()),
+        prop(
+            DAV,
+            "supported-report-set",
+            ["C:calendar-query", "C:calendar-multiget"]
+                .map(|report| {
+                    format!(
+                        "<D:supported-report><D:report><{report}/></D:report></D:supported-report>"
+                    )
+                })
+                .concat(),
+        ),
+        prop(
+            CALDAV,
+            "supported-calendar-component-set",
+            "<C:comp name=\"VEVENT\"/>".to_string(),
+        ),
+    ]);
+    if let Some(ctag) = ctag {
+        props.push(prop(CALENDARSERVER, "getctag", ctag));
+    }
+    props
+}
+
+fn event_props(request: &DavRequest, entry: &TimeTableEntry) -> Vec<(PropName, String)> {
+    let mut props = common_props();
+    props.extend([
+        prop(DAV, "resourcetype", String::new()),
+        prop(
+            DAV,
+            "getcontenttype",
+            "text/calendar; charset=utf-8; component=VEVENT".to_string(),
+        ),
+        prop(DAV, "getetag", format!("\"{}\"", event_etag(entry))),
+    ]);
+    if request.wants(CALDAV, "calendar-data") {
+        props.push(prop(
+            CALDAV,
+            "calendar-data",
+            escape(&event_calendar(entry, Utc::now())).into_owned(),
+        ));
+    }
+    props
+}
+
+fn calendar_filter(home: Home, name: &str) -> TimetableFilter {
+    match home {
+        Home::Groups => TimetableFilter::new().groups([name]),
+        Home::Tutors => TimetableFilter::new().tutors([name]),
+    }
+}
+
+/// Entries of a calendar collection, sorted by beginning
+async fn calendar_entries(
+    coll_db: &Collection<TimeTableEntry>,
+    home: Home,
+    name: &str,
+) -> MongoResult<Vec<TimeTableEntry>> {
+    let mut entries = find_filtered(coll_db, &calendar_filter(home, name)).await?;
+    entries.sort_by_key(|entry| entry.get_datetime_beginning());
+    Ok(entries)
+}
+
+/// Entries of a calendar collection with one of the ids, in no particular order
+async fn calendar_events(
+    coll_db: &Collection<TimeTableEntry>,
+    home: Home,
+    name: &str,
+    ids: &[&str],
+) -> MongoResult<Vec<TimeTableEntry>> {
+    let query = doc! {"$and": [
+        calendar_filter(home, name).to_document(),
+        {"_id": {"$in": ids}},
+    ]};
+    let cursor: Cursor<TimeTableEntry> = coll_db.find(query, None).await?;
+    cursor.try_collect().await
+}
+
+/// `getctag` of every calendar collection of a home by its name, in a single query.
+///
+/// Entries are streamed rather than grouped by the database, which would hit its document and
+/// memory limits on a full semester, and only their etags are kept. Each is counted under every
+/// name its calendar filter matches it by, as raw and normalised groups or as raw names and ids
+/// of tutors, so the tags are the same as those computed out of `calendar_entries`.
+async fn calendar_ctags(
+    coll_db: &Collection<TimeTableEntry>,
+    home: Home,
+) -> MongoResult<HashMap<String, String>> {
+    let mut cursor: Cursor<TimeTableEntry> = coll_db.find(None, None).await?;
+    let mut etags: HashMap<String, Vec<String>> = HashMap::new();
+    while let Some(entry) = cursor.try_next().await? {
+        let etag = event_etag(&entry);
+        for name in calendar_names(home, &entry) {
+            etags
+                .entry(name.to_string())
+                .or_default()
+                .push(etag.clone());
+        }
+    }
+    Ok(etags
+        .into_iter()
+        .map(|(name, etags)| (name, etags_ctag(etags)))
+        .collect())
+}
+
+/// Names of the calendar collections of a home an entry belongs to
+fn calendar_names(home: Home, entry: &TimeTableEntry) -> HashSet<&str> {
+    match home {
+        Home::Groups => entry
+            .get_groups()
+            .unwrap_or_default()
+            .iter()
+            .map(String::as_str)
+            .chain(entry.get_group_codes().iter().map(GroupCode::code))
+            .collect(),
+        Home::Tutors => entry
+            .get_persons()
+            .iter()
+            .map(String::as_str)
+            .chain(entry.get_tutors().iter().map(Person::id))
+            .collect(),
+    }
+}
+
+/// Response to a failed database query
+fn mongo_error() -> Response {
+    error!("{}", "MongoDB error!");
+    StatusCode::INTERNAL_SERVER_ERROR.into_response()
+}
+
+/// Name of a calendar collection shown by clients, tutors are named after any of their spellings
+fn calendar_name(home: Home, name: &str, entries: &[TimeTableEntry]) -> String {
+    let owner = match home {
+        Home::Groups => None,
+        Home::Tutors => entries
+            .iter()
+            .flat_map(|entry| entry.get_persons())
+            .find(|person| Person::id_of(person) == name),
+    };
+    format!("PJATK {}", owner.map_or(name, String::as_str))
+}
+
+#[handler]
+async fn dav(req: &Request, body: Body, coll_db: Data<&Collection<TimeTableEntry>>) -> Response {
+    let resource = match Resource::parse(req.uri().path()) {
+        Some(resource) => resource,
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+    let depth_one = req
+        .headers()
+        .get("Depth")
+        .and_then(|depth| depth.to_str().ok())
+        != Some("0");
+    match req.method().as_str() {
+        "OPTIONS" => Response::builder()
+            .header("DAV", "1, calendar-access")
+            .header(header::ALLOW, "OPTIONS, GET, HEAD, PROPFIND, REPORT")
+            .finish(),
+        "GET" | "HEAD" => get(&coll_db, resource).await,
+        method @ ("PROPFIND" | "REPORT") => {
+            let request = match body.into_string().await.ok().and_then(|body| {
+                if body.trim().is_empty() {
+                    Some(DavRequest::default())
+                } else {
+                    DavRequest::parse(&body)
+                }
+            }) {
+                Some(request) => request,
+                None => return StatusCode::BAD_REQUEST.into_response(),
+            };
+            if method == "PROPFIND" {
+                propfind(&coll_db, resource, &request, depth_one).await
+            } else {
+                report(&coll_db, resource, &request).await
+            }
+        }
+        _ => Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .header(header::ALLOW, "OPTIONS, GET, HEAD, PROPFIND, REPORT")
+            .finish(),
+    }
+}
+
+async fn get(coll_db: &Collection<TimeTableEntry>, resource: Resource) -> Response {
+    let (entries, calendar) = match &resource {
+        Resource::Calendar(home, name) => {
+            let entries = match calendar_entries(coll_db, *home, name).await {
+                Ok(entries) => entries,
+                Err(_) => return mongo_error(),
+            };
+            let calendar =
+                entries_calendar(&calendar_name(*home, name, &entries), &entries, Utc::now());
+            (entries, calendar)
+        }
+        Resource::Event(home, name, id) => {
+            let entries = match calendar_events(coll_db, *home, name, &[id]).await {
+                Ok(entries) => entries,
+                Err(_) => return mongo_error(),
+            };
+            match entries.first() {
+                Some(entry) => {
+                    return Response::builder()
+                        .content_type("text/calendar; charset=utf-8")
+                        .header(header::ETAG, format!("\"{}\"", event_etag(entry)))
+                        .body(event_calendar(entry, Utc::now()))
+                }
+                None => return StatusCode::NOT_FOUND.into_response(),
+            }
+        }
+        _ => return StatusCode::METHOD_NOT_ALLOWED.into_response(),
+    };
+    if entries.is_empty() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    Response::builder()
+        .content_type("text/calendar; charset=utf-8")
+        .body(calendar)
+}
+
+async fn propfind(
+    coll_db: &Collection<TimeTableEntry>,
+    resource: Resource,
+    request: &DavRequest,
+    depth_one: bool,
+) -> Response {
+    let mut multistatus = Multistatus::new();
+    match resource {
+        Resource::Principal => {
+            multistatus.response(&principal_href(), request, principal_props());
+            if depth_one {
+                for home in [Home::Groups, Home::Tutors] {
+                    multistatus.response(&home_href(home), request, home_props(home));
+                }
+            }
+        }
+        Resource::Home(home) => {
+            multistatus.response(&home_href(home), request, home_props(home));
+            if depth_one {
+                let calendars = match home {
+                    Home::Groups => find_groups(coll_db).await.map(|groups| {
+                        groups
+                            .into_iter()
+                            .map(|group| (group.clone(), group))
+                            .collect()
+                    }),
+                    Home::Tutors => find_tutors(coll_db).await.map(|tutors| {
+                        tutors
+                            .iter()
+                            .map(|tutor| {
+                                let person = tutor.person();
+                                let names = person
+                                    .titles()
+                                    .iter()
+                                    .chain([person.surname().to_string()].iter())
+                                    .chain(person.given_names())
+                                    .cloned()
+                                    .collect::<Vec<_>>();
+                                (person.id().to_string(), names.join(" "))
+                            })
+                            .collect::<Vec<_>>()
+                    }),
+                };
+                let calendars: Vec<(String, String)> = match calendars {
+                    Ok(calendars) => calendars,
+                    Err(_) => return mongo_error(),
+                };
+                let mut ctags = if request.wants(CALENDARSERVER, "getctag") {
+                    match calendar_ctags(coll_db, home).await {
+                        Ok(ctags) => Some(ctags),
+                        Err(_) => return mongo_error(),
+                    }
+                } else {
+                    None
+                };
+                for (name, owner) in calendars {
+                    let ctag = ctags
+                        .as_mut()
+                        .map(|ctags| ctags.remove(&name).unwrap_or_else(|| calendar_ctag(&[])));
+                    multistatus.response(
+                        &calendar_href(home, &name),
+                        request,
+                        calendar_props(&format!("PJATK {owner}"), ctag),
+                    );
+                }
+            }
+        }
+        Resource::Calendar(home, name) => {
+            let entries = match calendar_entries(coll_db, home, &name).await {
+                Ok(entries) => entries,
+                Err(_) => return mongo_error(),
+            };
+            if entries.is_empty() {
+                return StatusCode::NOT_FOUND.into_response();
+            }
+            let ctag = request
+                .wants(CALENDARSERVER, "getctag")
+                .then(|| calendar_ctag(&entries));
+            multistatus.response(
+                &calendar_href(home, &name),
+                request,
+                calendar_props(&calendar_name(home, &name, &entries), ctag),
+            );
+            if depth_one {
+                for entry in &entries {
+                    multistatus.response(
+                        &event_href(home, &name, entry),
+                        request,
+                        event_props(request, entry),
+                    );
+                }
+            }
+        }
+        Resource::Event(home, name, id) => {
+            let entries = match calendar_events(coll_db, home, &name, &[&id]).await {
+                Ok(entries) => entries,
+                Err(_) => return mongo_error(),
+            };
+            match entries.first() {
+                Some(entry) => multistatus.response(
+                    &event_href(home, &name, entry),
+                    request,
+                    event_props(request, entry),
+                ),
+                None => return StatusCode::NOT_FOUND.into_response(),
+            }
+        }
+    }
+    multistatus.finish()
+}
+
+/// `calendar-query` filtered by a time range, or `calendar-multiget`, of a calendar collection
+async fn report(
+    coll_db: &Collection<TimeTableEntry>,
+    resource: Resource,
+    request: &DavRequest,
+) -> Response {
+    let (home, name) = match resource {
+        Resource::Calendar(home, name) => (home, name),
+        _ => return StatusCode::FORBIDDEN.into_response(),
+    };
+    let mut multistatus = Multistatus::new();
+    match request.root.as_str() {
+        "calendar-query" => {
+            // Every entry is a single VEVENT, so any other component matches nothing
+            if request
+                .components
+                .iter()
+                .any(|component| !matches!(component.as_str(), "VCALENDAR" | "VEVENT"))
+            {
+                return multistatus.finish();
+            }
+            // The default range mode overlaps the time range the way RFC 4791 does for events
+            let mut filter = calendar_filter(home, &name);
+            if let Some(start) = request.start {
+                filter = filter.date_from(start);
+            }
+            if let Some(end) = request.end {
+                filter = filter.date_to(end);
+            }
+            let entries = match find_filtered(coll_db, &filter).await {
+                Ok(entries) => entries,
+                Err(_) => return mongo_error(),
+            };
+            for entry in &entries {
+                multistatus.response(
+                    &event_href(home, &name, entry),
+                    request,
+                    event_props(request, entry),
+                );
+            }
+        }
+        "calendar-multiget" => {
+            // Ids of the events of this calendar, `None` for hrefs of other resources
+            let ids: Vec<Option<String>> = request
+                .hrefs
+                .iter()
+                .map(|href| {
+                    // Hrefs are either paths or whole URLs
+                    let path = href
+                        .find(CALDAV_ROOT)
+                        .map_or("", |start| &href[start + CALDAV_ROOT.len()..]);
+                    match Resource::parse(path) {
+                        Some(Resource::Event(event_home, event_name, id))
+                            if event_home == home && event_name == name =>
+                        {
+                            Some(id)
+                        }
+                        _ => None,
+                    }
+                })
+                .collect();
+            let wanted: Vec<&str> = ids.iter().flatten().map(String::as_str).collect();
+            let entries = match calendar_events(coll_db, home, &name, &wanted).await {
+                Ok(entries) => entries,
+                Err(_) => return mongo_error(),
+            };
+            for (href, id) in request.hrefs.iter().zip(&ids) {
+                let entry = id
+                    .as_ref()
+                    .and_then(|id| entries.iter().find(|entry| entry.get_id() == id));
+                match entry {
+                    Some(entry) => multistatus.response(href, request, event_props(request, entry)),
+                    None => multistatus.not_found(href),
+                }
+            }
+        }
+        _ => return StatusCode::FORBIDDEN.into_response(),
+    }
+    multistatus.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(request: &DavRequest) -> Vec<(&str, &str)> {
+        request
+            .props
+            .iter()
+            .flatten()
+            .map(|prop| (prop.namespace.as_str(), prop.name.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn empty_body_is_allprop() {
+        let request = DavRequest::parse("").unwrap();
+
+        assert!(request.props.is_none());
+        assert!(request.wants(DAV, "displayname"));
+        assert!(!request.wants(CALDAV, "calendar-data"));
+    }
+
+    #[test]
+    fn propfind_lists_requested_props() {
+        let request = DavRequest::parse(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+            <D:propfind xmlns:D="DAV:" xmlns:CS="http://calendarserver.org/ns/">
+                <D:prop><D:displayname/><CS:getctag/><X:color xmlns:X="urn:x"/></D:prop>
+            </D:propfind>"#,
+        )
+        .unwrap();
+
+        assert_eq!(request.root, "propfind");
+        assert_eq!(
+            names(&request),
+            [
+                (DAV, "displayname"),
+                (CALENDARSERVER, "getctag"),
+                ("urn:x", "color")
+            ]
+        );
+        assert!(request.wants(CALENDARSERVER, "getctag"));
+        assert!(!request.wants(DAV, "resourcetype"));
+    }
+
+    #[test]
+    fn calendar_query_reads_filter() {
+        let request = DavRequest::parse(
+            r#"<C:calendar-query xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav">
+                <D:prop><D:getetag/><C:calendar-data/></D:prop>
+                <C:filter>
+                    <C:comp-filter name="VCALENDAR">
+                        <C:comp-filter name="VEVENT">
+                            <C:time-range start="20240304T070000Z" end="20240311T070000Z"/>
+                        </C:comp-filter>
+                    </C:comp-filter>
+                </C:filter>
+            </C:calendar-query>"#,
+        )
+        .unwrap();
+
+        assert_eq!(request.root, "calendar-query");
+        assert_eq!(request.components, ["VCALENDAR", "VEVENT"]);
+        assert_eq!(
+            request.start,
+            Some(Utc.with_ymd_and_hms(2024, 3, 4, 7, 0, 0).unwrap())
+        );
+        assert_eq!(
+            request.end,
+            Some(Utc.with_ymd_and_hms(2024, 3, 11, 7, 0, 0).unwrap())
+        );
+        assert!(request.wants(CALDAV, "calendar-data"));
+    }
+
+    #[test]
+    fn calendar_multiget_reads_hrefs() {
+        let request = DavRequest::parse(
+            r#"<C:calendar-multiget xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav">
+                <D:prop><D:getetag/></D:prop>
+                <D:href>/caldav/groups/WIs%20I.2%20-%2046c/abc.ics</D:href>
+                <D:href>/caldav/groups/WIs%20I.2%20-%2046c/a&amp;b.ics</D:href>
+            </C:calendar-multiget>"#,
+        )
+        .unwrap();
+
+        assert_eq!(request.root, "calendar-multiget");
+        assert_eq!(
+            request.hrefs,
+            [
+                "/caldav/groups/WIs%20I.2%20-%2046c/abc.ics",
+                "/caldav/groups/WIs%20I.2%20-%2046c/a&b.ics"
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_body_is_rejected() {
+        assert!(DavRequest::parse(r#"<D:propfind xmlns:D="DAV:"><D:prop></D:propfind>"#).is_none());
+    }
+
+    #[test]
+    fn multistatus_splits_found_and_missing_props() {
+        let request = DavRequest::parse(
+            r#"<D:propfind xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav">
+                <D:prop><D:displayname/><C:calendar-color/></D:prop>
+            </D:propfind>"#,
+        )
+        .unwrap();
+        let mut multistatus = Multistatus::new();
+        multistatus.response(
+            "/caldav/groups/a&b/",
+            &request,
+            vec![
+                prop(DAV, "displayname", "PJATK a &amp; b".to_string()),
+                prop(DAV, "resourcetype", "<D:collection/>".to_string()),
+            ],
+        );
+
+        assert!(multistatus.body.ends_with(concat!(
+            "<D:response><D:href>/caldav/groups/a&amp;b/</D:href>",
+            "<D:propstat><D:prop><D:displayname>PJATK a &amp; b</D:displayname></D:prop>",
+            "<D:status>HTTP/1.1 200 OK</D:status></D:propstat>",
+            "<D:propstat><D:prop><C:calendar-color></C:calendar-color></D:prop>",
+            "<D:status>HTTP/1.1 404 Not Found</D:status></D:propstat>",
+            "</D:response>"
+        )));
+    }
+
+    #[test]
+    fn multistatus_allprop_leaves_calendar_data_out() {
+        let request = DavRequest::parse("").unwrap();
+        let mut multistatus = Multistatus::new();
+        multistatus.response(
+            "/caldav/",
+            &request,
+            vec![
+                prop(DAV, "displayname", "PJATK".to_string()),
+                prop(CALDAV, "calendar-data", "BEGIN:VCALENDAR".to_string()),
+            ],
+        );
+        multistatus.not_found("/caldav/groups/none/");
+
+        assert!(multistatus.body.ends_with(concat!(
+            "<D:response><D:href>/caldav/</D:href>",
+            "<D:propstat><D:prop><D:displayname>PJATK</D:displayname></D:prop>",
+            "<D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>",
+            "<D:response><D:href>/caldav/groups/none/</D:href>",
+            "<D:status>HTTP/1.1 404 Not Found</D:status></D:response>"
+        )));
+    }
+
+    #[test]
+    fn unknown_namespace_is_declared() {
+        let (open, close) = prop("urn:x", "color", String::new()).0.tags();
+
+        assert_eq!(open, r#"<color xmlns="urn:x">"#);
+        assert_eq!(close, "</color>");
+    }
+}
@@ -0,0 +1,320 @@
+#![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::{Europe::Warsaw, Tz};
+use poem_openapi::Enum;
+
+/// End of the date range a date is given for, days cover the whole day either way
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RangeEnd {
+    From,
+    To,
+}
+
+/// Range relative to the current day in Europe/Warsaw
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum)]
+#[oai(rename_all = "snake_case")]
+pub(crate) enum RelativeRange {
+    Today,
+    Tomorrow,
+    /// Monday to Sunday
+    ThisWeek,
+    NextWeek,
+    ThisMonth,
+    /// From now until the end of the semester, winter ones end with February and summer ones with September
+    RestOfSemester,
+}
+
+impl RelativeRange {
+    /// Beginning and end of the range at `now`, `None` past the end of time
+    pub(crate) fn resolve(self, now: DateTime<Utc>) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let today = now.with_timezone(&Warsaw).date_naive();
+        let monday = today - Duration::days(i64::from(today.weekday().num_days_from_monday()));
+        // Days the range begins on and ends before
+        let (first, last) = match self {
+            Self::Today => (today, today.succ_opt()?),
+            Self::Tomorrow => (today.succ_opt()?, today.succ_opt()?.succ_opt()?),
+            Self::ThisWeek => (monday, monday + Duration::weeks(1)),
+            Self::NextWeek => (monday + Duration::weeks(1), monday + Duration::weeks(2)),
+            Self::ThisMonth => {
+                let first = today.with_day(1)?;
+                let last = match today.month() {
+                    12 => NaiveDate::from_ymd_opt(today.year() + 1, 1, 1)?,
+                    month => NaiveDate::from_ymd_opt(today.year(), month + 1, 1)?,
+                };
+                (first, last)
+            }
+            Self::RestOfSemester => {
+                let last = match today.month() {
+                    1 | 2 => NaiveDate::from_ymd_opt(today.year(), 3, 1)?,
+                    3..=9 => NaiveDate::from_ymd_opt(today.year(), 10, 1)?,
+                    _ => NaiveDate::from_ymd_opt(today.year() + 1, 3, 1)?,
+                };
+                return Some((now, midnight(last)?));
+            }
+        };
+        Some((midnight(first)?, midnight(last)?))
+    }
+}
+
+/// Parses a time zone given by its IANA name, e.g. `Europe/Warsaw`
+pub(crate) fn parse_timezone(timezone: &str) -> Result<Tz, String> {
+    timezone
+        .parse()
+        .map_err(|_| format!("`{timezone}` is not an IANA time zone, e.g. `Europe/Warsaw`"))
+}
+
+/// Parses a unix timestamp in seconds, an ISO 8601 datetime or a `YYYY-MM-DD` day
+///
+/// Datetimes without an offset and days are local to `timezone`. A day as the end of a range
+/// lasts until the following midnight.
+pub(crate) fn parse_date(
+    name: &str,
+    date: &str,
+    timezone: Tz,
+    end: RangeEnd,
+) -> Result<DateTime<Utc>, String> {
+    let date = date.trim();
+    let parsed = if let Ok(seconds) = date.parse::<i64>() {
+        Utc.timestamp_opt(seconds, 0).single()
+    } else if let Ok(datetime) = DateTime::parse_from_rfc3339(date) {
+        Some(datetime.with_timezone(&Utc))
+    } else if let Some(datetime) = parse_local_datetime(date) {
+        local(datetime, timezone)
+    } else if let Ok(day) = NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+        let day = match end {
+            RangeEnd::From => Some(day),
+            RangeEnd::To => day.checked_add_signed(Duration::days(1)),
+        };
+        day.and_then(|day| local(day.and_hms_opt(0, 0, 0)?, timezone))
+    } else {
+        return Err(format!(
+            "`{name}` has to be a unix timestamp, an ISO 8601 datetime or a `YYYY-MM-DD` day"
+        ));
+    };
+    parsed.ok_or_else(|| format!("`{name}` is out of range"))
+}
+
+fn parse_local_datetime(datetime: &str) -> Option<NaiveDateTime> {
+    ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%dT%H:%M"]
+        .into_iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(datetime, format).ok())
+}
+
+/// Local time in the time zone, times skipped by a DST change are shifted forward by the gap
+fn local(datetime: NaiveDateTime, timezone: Tz) -> Option<DateTime<Utc>> {
+    timezone
+        .from_local_datetime(&datetime)
+        .earliest()
+        .or_else(|| {
+            let shifted = datetime.checked_add_signed(Duration::hours(1))?;
+            timezone.from_local_datetime(&shifted).earliest()
+        })
+        .map(|datetime| datetime.with_timezone(&Utc))
+}
+
+/// Beginning of the day in Europe/Warsaw
+fn midnight(day: NaiveDate) -> Option<DateTime<Utc>> {
+    local(day.and_hms_opt(0, 0, 0)?, Warsaw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn this_week_spans_spring_forward() {
+        // Clocks go forward on Sunday, 31 March 2024, so the week is an hour short
+        let range = RelativeRange::ThisWeek.resolve(utc(2024, 3, 27, 12, 0));
+        assert_eq!(
+            range,
+            Some((utc(2024, 3, 24, 23, 0), utc(2024, 3, 31, 22, 0)))
+        );
+    }
+
+    #[test]
+    fn next_week_spans_fall_back() {
+        // Clocks go back on Sunday, 27 October 2024, so the week is an hour long
+        let range = RelativeRange::NextWeek.resolve(utc(2024, 10, 16, 12, 0));
+        assert_eq!(
+            range,
+            Some((utc(2024, 10, 20, 22, 0), utc(2024, 10, 27, 23, 0)))
+        );
+    }
+
+    #[test]
+    fn this_week_on_sunday_evening() {
+        let sunday = RelativeRange::ThisWeek.resolve(utc(2024, 3, 10, 21, 30));
+        assert_eq!(
+            sunday,
+            Some((utc(2024, 3, 3, 23, 0), utc(2024, 3, 10, 23, 0)))
+        );
+
+        // Half past midnight on Monday in Warsaw, still Sunday in UTC
+        let monday = RelativeRange::ThisWeek.resolve(utc(2024, 3, 10, 23, 30));
+        assert_eq!(
+            monday,
+            Some((utc(2024, 3, 10, 23, 0), utc(2024, 3, 17, 23, 0)))
+        );
+    }
+
+    #[test]
+    fn today_on_daylight_saving_day() {
+        let range = RelativeRange::Today.resolve(utc(2024, 3, 31, 10, 0));
+        assert_eq!(
+            range,
+            Some((utc(2024, 3, 30, 23, 0), utc(2024, 3, 31, 22, 0)))
+        );
+    }
+
+    #[test]
+    fn tomorrow_rolls_over_month_and_year() {
+        let february = RelativeRange::Tomorrow.resolve(utc(2024, 1, 31, 12, 0));
+        assert_eq!(
+            february,
+            Some((utc(2024, 1, 31, 23, 0), utc(2024, 2, 1, 23, 0)))
+        );
+
+        let new_year = RelativeRange::Tomorrow.resolve(utc(2024, 12, 30, 12, 0));
+        assert_eq!(
+            new_year,
+            Some((utc(2024, 12, 30, 23, 0), utc(2024, 12, 31, 23, 0)))
+        );
+
+        // Already the first of January in Warsaw
+        let today = RelativeRange::Today.resolve(utc(2024, 12, 31, 23, 30));
+        assert_eq!(
+            today,
+            Some((utc(2024, 12, 31, 23, 0), utc(2025, 1, 1, 23, 0)))
+        );
+    }
+
+    #[test]
+    fn this_month_rolls_over_year() {
+        let december = RelativeRange::ThisMonth.resolve(utc(2024, 12, 15, 12, 0));
+        assert_eq!(
+            december,
+            Some((utc(2024, 11, 30, 23, 0), utc(2024, 12, 31, 23, 0)))
+        );
+
+        let march = RelativeRange::ThisMonth.resolve(utc(2024, 3, 15, 12, 0));
+        assert_eq!(
+            march,
+            Some((utc(2024, 2, 29, 23, 0), utc(2024, 3, 31, 22, 0)))
+        );
+    }
+
+    #[test]
+    fn rest_of_semester_ends_with_february_or_september() {
+        let now = utc(2024, 11, 20, 12, 0);
+        let winter = RelativeRange::RestOfSemester.resolve(now);
+        assert_eq!(winter, Some((now, utc(2025, 2, 28, 23, 0))));
+
+        let now = utc(2025, 2, 10, 12, 0);
+        let february = RelativeRange::RestOfSemester.resolve(now);
+        assert_eq!(february, Some((now, utc(2025, 2, 28, 23, 0))));
+
+        let now = utc(2024, 5, 10, 12, 0);
+        let summer = RelativeRange::RestOfSemester.resolve(now);
+        assert_eq!(summer, Some((now, utc(2024, 9, 30, 22, 0))));
+    }
+
+    #[test]
+    fn rest_of_semester_switches_at_local_midnight() {
+        // The first of October has begun in Warsaw, so the winter semester has too
+        let now = utc(2024, 9, 30, 22, 30);
+        let range = RelativeRange::RestOfSemester.resolve(now);
+        assert_eq!(range, Some((now, utc(2025, 2, 28, 23, 0))));
+    }
+
+    fn parse(date: &str, end: RangeEnd) -> Result<DateTime<Utc>, String> {
+        parse_date("date_from", date, Warsaw, end)
+    }
+
+    #[test]
+    fn day_covers_whole_local_day() {
+        assert_eq!(
+            parse("2024-03-04", RangeEnd::From),
+            Ok(utc(2024, 3, 3, 23, 0))
+        );
+        assert_eq!(
+            parse("2024-03-04", RangeEnd::To),
+            Ok(utc(2024, 3, 4, 23, 0))
+        );
+        // The following midnight is already in summer time
+        assert_eq!(
+            parse("2024-03-31", RangeEnd::To),
+            Ok(utc(2024, 3, 31, 22, 0))
+        );
+        assert_eq!(
+            parse_date("date_to", " 2024-03-04 ", Tz::UTC, RangeEnd::To),
+            Ok(utc(2024, 3, 5, 0, 0))
+        );
+    }
+
+    #[test]
+    fn local_datetime_in_spring_forward_gap_is_shifted() {
+        // 2:30 doesn't exist on 31 March 2024 in Warsaw, 3:30 summer time does
+        assert_eq!(
+            parse("2024-03-31T02:30", RangeEnd::From),
+            Ok(utc(2024, 3, 31, 1, 30))
+        );
+        assert_eq!(
+            parse("2024-03-31T01:30:00", RangeEnd::From),
+            Ok(utc(2024, 3, 31, 0, 30))
+        );
+    }
+
+    #[test]
+    fn ambiguous_local_datetime_is_the_earlier() {
+        assert_eq!(
+            parse("2024-10-27T02:30", RangeEnd::From),
+            Ok(utc(2024, 10, 27, 0, 30))
+        );
+    }
+
+    #[test]
+    fn rfc3339_keeps_its_offset() {
+        assert_eq!(
+            parse("2024-03-04T08:00:00+05:30", RangeEnd::From),
+            Ok(utc(2024, 3, 4, 2, 30))
+        );
+        assert_eq!(
+            parse("2024-03-04T08:00:00Z", RangeEnd::To),
+            Ok(utc(2024, 3, 4, 8, 0))
+        );
+    }
+
+    #[test]
+    fn unix_timestamp_is_in_seconds() {
+        assert_eq!(
+            parse("1709539200", RangeEnd::From),
+            Ok(utc(2024, 3, 4, 8, 0))
+        );
+    }
+
+    #[test]
+    fn unix_timestamp_overflow_is_an_error() {
+        assert_eq!(
+            parse("99999999999999999", RangeEnd::From),
+            Err("`date_from` is out of range".to_string())
+        );
+        assert!(parse("99999999999999999999", RangeEnd::From).is_err());
+    }
+
+    #[test]
+    fn garbage_is_an_error() {
+        for date in ["next tuesday", "", "2024-13-01", "04.03.2024"] {
+            assert_eq!(
+                parse(date, RangeEnd::From),
+                Err("`date_from` has to be a unix timestamp, an ISO 8601 datetime or a `YYYY-MM-DD` day".to_string()),
+                "{date}"
+            );
+        }
+    }
+}
@@ -0,0 +1,391 @@
+#![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
+
+use chrono::{Datelike, NaiveDate, NaiveTime, Timelike};
+use chrono_tz::Europe::Warsaw;
+use rust_xlsxwriter::{ExcelDateTime, Format, Workbook, XlsxError};
+use timetable::timetable::TimeTableEntry;
+
+/// Separator of values of multi-valued fields, the same one the API takes lists with
+const LIST_SEPARATOR: &str = "; ";
+
+/// Column of an exported timetable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Column {
+    Id,
+    Date,
+    TimeBeginning,
+    TimeEnding,
+    Title,
+    Subjects,
+    SubjectCodes,
+    TypeOf,
+    Kind,
+    Persons,
+    Groups,
+    Building,
+    Room,
+    Students,
+    Status,
+    Details,
+}
+
+const COLUMNS: [Column; 16] = [
+    Column::Id,
+    Column::Date,
+    Column::TimeBeginning,
+    Column::TimeEnding,
+    Column::Title,
+    Column::Subjects,
+    Column::SubjectCodes,
+    Column::TypeOf,
+    Column::Kind,
+    Column::Persons,
+    Column::Groups,
+    Column::Building,
+    Column::Room,
+    Column::Students,
+    Column::Status,
+    Column::Details,
+];
+
+/// Columns exported when none are given
+const DEFAULT_COLUMNS: [Column; 9] = [
+    Column::Date,
+    Column::TimeBeginning,
+    Column::TimeEnding,
+    Column::Subjects,
+    Column::TypeOf,
+    Column::Persons,
+    Column::Groups,
+    Column::Building,
+    Column::Room,
+];
+
+/// Value of a cell, dates and times in Europe/Warsaw time
+enum Cell {
+    Text(String),
+    Date(NaiveDate),
+    Time(NaiveTime),
+    Number(u32),
+    Empty,
+}
+
+impl Column {
+    /// Name of the column in the `columns` parameter
+    fn name(self) -> &'static str {
+        match self {
+            Self::Id => "id",
+            Self::Date => "date",
+            Self::TimeBeginning => "time_beginning",
+            Self::TimeEnding => "time_ending",
+            Self::Title => "title",
+            Self::Subjects => "subjects",
+            Self::SubjectCodes => "subject_codes",
+            Self::TypeOf => "type_of",
+            Self::Kind => "kind",
+            Self::Persons => "persons",
+            Self::Groups => "groups",
+            Self::Building => "building",
+            Self::Room => "room",
+            Self::Students => "students",
+            Self::Status => "status",
+            Self::Details => "details",
+        }
+    }
+    /// Header of the column in the exported file
+    fn header(self) -> &'static str {
+        match self {
+            Self::Id => "Id",
+            Self::Date => "Data",
+            Self::TimeBeginning => "Początek",
+            Self::TimeEnding => "Koniec",
+            Self::Title => "Tytuł",
+            Self::Subjects => "Przedmioty",
+            Self::SubjectCodes => "Kody przedmiotów",
+            Self::TypeOf => "Typ zajęć",
+            Self::Kind => "Rodzaj",
+            Self::Persons => "Prowadzący",
+            Self::Groups => "Grupy",
+            Self::Building => "Budynek",
+            Self::Room => "Sala",
+            Self::Students => "Liczba studentów",
+            Self::Status => "Status",
+            Self::Details => "Szczegóły",
+        }
+    }
+    fn cell(self, entry: &TimeTableEntry) -> Cell {
+        let text = |text: &str| {
+            if text.is_empty() {
+                Cell::Empty
+            } else {
+                Cell::Text(text.to_string())
+            }
+        };
+        let beginning = entry.get_datetime_beginning().with_timezone(&Warsaw);
+        let ending = entry.get_datetime_ending().with_timezone(&Warsaw);
+        match self {
+            Self::Id => text(entry.get_id()),
+            Self::Date => Cell::Date(beginning.date_naive()),
+            Self::TimeBeginning => Cell::Time(beginning.time()),
+            Self::TimeEnding => Cell::Time(ending.time()),
+            Self::Title => text(entry.get_title().unwrap_or_default()),
+            Self::Subjects => text(&entry.get_subjects().join(LIST_SEPARATOR)),
+            Self::SubjectCodes => text(&entry.get_subject_codes().join(LIST_SEPARATOR)),
+            Self::TypeOf => text(entry.get_type_of()),
+            Self::Kind => text(entry.get_kind().as_str()),
+            Self::Persons => text(&entry.get_persons().join(LIST_SEPARATOR)),
+            Self::Groups => text(&entry.get_groups().unwrap_or_default().join(LIST_SEPARATOR)),
+            Self::Building => text(entry.get_building()),
+            Self::Room => text(entry.get_room()),
+            Self::Students => entry
+                .get_students_count()
+                .and_then(|students| students.enrolled())
+                .map_or(Cell::Empty, Cell::Number),
+            Self::Status => {
+                let status = entry.get_status();
+                let labels: Vec<&str> = [
+                    (status.cancelled(), "odwołane"),
+                    (status.moved(), "przeniesione"),
+                    (status.online_only(), "zdalnie"),
+                    (status.substitute(), "zastępstwo"),
+                ]
+                .into_iter()
+                .filter(|(set, _)| *set)
+                .map(|(_, label)| label)
+                .collect();
+                text(&labels.join(LIST_SEPARATOR))
+            }
+            Self::Details => text(entry.get_details().unwrap_or_default()),
+        }
+    }
+}
+
+/// Parses columns separated by `;`, returns the unknown name on failure
+pub(crate) fn parse_columns(columns: Option<&str>) -> Result<Vec<Column>, String> {
+    let columns = match columns {
+        Some(columns) => columns,
+        None => return Ok(DEFAULT_COLUMNS.to_vec()),
+    };
+    let columns = columns
+        .split_terminator(';')
+        .map(str::trim)
+        .filter(|column| !column.is_empty())
+        .map(|name| {
+            COLUMNS
+                .into_iter()
+                .find(|column| column.name() == name)
+                .ok_or_else(|| name.to_string())
+        })
+        .collect::<Result<Vec<Column>, String>>()?;
+    if columns.is_empty() {
+        Ok(DEFAULT_COLUMNS.to_vec())
+    } else {
+        Ok(columns)
+    }
+}
+
+/// RFC 4180 CSV with a header row, prefixed with a byte order mark so Excel reads it as UTF-8
+pub(crate) fn to_csv(entries: &[TimeTableEntry], columns: &[Column]) -> String {
+    let mut csv = String::from('\u{feff}');
+    let mut row = |fields: Vec<String>| {
+        let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        csv.push_str(&fields.join(","));
+        csv.push_str("\r\n");
+    };
+    row(columns
+        .iter()
+        .map(|column| column.header().to_string())
+        .collect());
+    for entry in entries {
+        row(columns
+            .iter()
+            .map(|column| match column.cell(entry) {
+                Cell::Text(text) => text,
+                Cell::Date(date) => date.format("%Y-%m-%d").to_string(),
+                Cell::Time(time) => time.format("%H:%M").to_string(),
+                Cell::Number(number) => number.to_string(),
+                Cell::Empty => String::new(),
+            })
+            .collect());
+    }
+    csv
+}
+
+/// Quotes a field if it has a separator, quote or line break in it
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Workbook with a single sheet, dates and times are stored as such and not as text
+pub(crate) fn to_xlsx(
+    entries: &[TimeTableEntry],
+    columns: &[Column],
+) -> Result<Vec<u8>, XlsxError> {
+    let header_format = Format::new().set_bold();
+    let date_format = Format::new().set_num_format("yyyy-mm-dd");
+    let time_format = Format::new().set_num_format("hh:mm");
+
+    let mut workbook = Workbook::new();
+    let worksheet = workbook.add_worksheet();
+    worksheet.set_name("Plan")?;
+    for (col, column) in (0u16..).zip(columns) {
+        worksheet.write_string_with_format(0, col, column.header(), &header_format)?;
+    }
+    for (row, entry) in (1u32..).zip(entries) {
+        for (col, column) in (0u16..).zip(columns) {
+            match column.cell(entry) {
+                Cell::Text(text) => {
+                    worksheet.write_string(row, col, text)?;
+                }
+                Cell::Date(date) => {
+                    let date = ExcelDateTime::from_ymd(
+                        date.year() as u16,
+                        date.month() as u8,
+                        date.day() as u8,
+                    )?;
+                    worksheet.write_datetime_with_format(row, col, date, &date_format)?;
+                }
+                Cell::Time(time) => {
+                    let time = ExcelDateTime::from_hms(time.hour() as u16, time.minute() as u8, 0)?;
+                    worksheet.write_datetime_with_format(row, col, time, &time_format)?;
+                }
+                Cell::Number(number) => {
+                    worksheet.write_number(row, col, number)?;
+                }
+                Cell::Empty => {}
+            }
+        }
+    }
+    if !columns.is_empty() {
+        worksheet.set_freeze_panes(1, 0)?;
+        worksheet.autofilter(0, 0, entries.len() as u32, columns.len() as u16 - 1)?;
+    }
+    worksheet.autofit();
+    workbook.save_to_buffer()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read};
+
+    use chrono::{Duration, TimeZone, Utc};
+    use timetable::fixtures::mock_entry_at;
+
+    use super::*;
+
+    fn entry() -> TimeTableEntry {
+        let beginning = Utc.with_ymd_and_hms(2024, 3, 4, 7, 30, 0).unwrap();
+        TimeTableEntry::builder()
+            .title("Wykład, \"GUI\"\nsala zmieniona")
+            .persons(["Niezgoda Adam", "Tomaszewski Michał"])
+            .type_of("Wykład")
+            .building("B2020")
+            .room("B/227")
+            .datetime_beginning(beginning)
+            .datetime_ending(beginning + Duration::minutes(90))
+            .build()
+            .unwrap()
+    }
+
+    /// Value of a cell in the XML of a worksheet
+    fn cell_value<'a>(sheet: &'a str, reference: &str) -> Option<&'a str> {
+        let cell = &sheet[sheet.find(&format!("<c r=\"{reference}\""))?..];
+        let cell = &cell[..cell.find("</c>")?];
+        let value = &cell[cell.find("<v>")? + 3..];
+        Some(&value[..value.find("</v>")?])
+    }
+
+    #[test]
+    fn csv_starts_with_byte_order_mark() {
+        let csv = to_csv(&[], &[Column::Date, Column::Room]);
+        assert_eq!(csv, "\u{feff}Data,Sala\r\n");
+    }
+
+    #[test]
+    fn csv_quotes_separators_quotes_and_line_breaks() {
+        let csv = to_csv(&[entry()], &[Column::Title, Column::Persons, Column::Room]);
+        assert_eq!(
+            csv,
+            "\u{feff}Tytuł,Prowadzący,Sala\r\n\
+             \"Wykład, \"\"GUI\"\"\nsala zmieniona\",Niezgoda Adam; Tomaszewski Michał,B/227\r\n"
+        );
+    }
+
+    #[test]
+    fn csv_dates_and_times_are_local() {
+        let csv = to_csv(
+            &[entry()],
+            &[Column::Date, Column::TimeBeginning, Column::TimeEnding],
+        );
+        assert_eq!(
+            csv,
+            "\u{feff}Data,Początek,Koniec\r\n2024-03-04,08:30,10:00\r\n"
+        );
+    }
+
+    #[test]
+    fn columns_default_when_none_are_given() {
+        assert_eq!(parse_columns(None), Ok(DEFAULT_COLUMNS.to_vec()));
+        assert_eq!(parse_columns(Some(" ; ")), Ok(DEFAULT_COLUMNS.to_vec()));
+        assert_eq!(
+            parse_columns(Some("date; room;")),
+            Ok(vec![Column::Date, Column::Room])
+        );
+    }
+
+    #[test]
+    fn unknown_column_is_named() {
+        assert_eq!(
+            parse_columns(Some("date;colour;room")),
+            Err("colour".to_string())
+        );
+        assert_eq!(parse_columns(Some("Date")), Err("Date".to_string()));
+    }
+
+    #[test]
+    fn xlsx_stores_dates_and_times_as_numbers() {
+        let beginning = Utc.with_ymd_and_hms(2024, 3, 4, 7, 30, 0).unwrap();
+        let xlsx = to_xlsx(
+            &[mock_entry_at(beginning)],
+            &[
+                Column::Date,
+                Column::TimeBeginning,
+                Column::TimeEnding,
+                Column::Room,
+            ],
+        )
+        .unwrap();
+        let mut archive = zip::ZipArchive::new(Cursor::new(xlsx)).unwrap();
+        let mut sheet = String::new();
+        archive
+            .by_name("xl/worksheets/sheet1.xml")
+            .unwrap()
+            .read_to_string(&mut sheet)
+            .unwrap();
+        let mut styles = String::new();
+        archive
+            .by_name("xl/styles.xml")
+            .unwrap()
+            .read_to_string(&mut styles)
+            .unwrap();
+
+        // Days since 30 December 1899 and fractions of a day, in Warsaw time
+        assert_eq!(cell_value(&sheet, "A2"), Some("45355"));
+        assert_eq!(
+            cell_value(&sheet, "B2").map(str::parse),
+            Some(Ok(8.5 / 24.0))
+        );
+        assert_eq!(
+            cell_value(&sheet, "C2").map(str::parse),
+            Some(Ok(10.5 / 24.0))
+        );
+        assert!(styles.contains("formatCode=\"yyyy-mm-dd\""));
+        assert!(styles.contains("formatCode=\"hh:mm\""));
+        // Text goes to the shared strings rather than being a number
+        assert!(sheet.contains("<c r=\"D2\" t=\"s\">"));
+    }
+}
@@ -0,0 +1,835 @@
+#![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
+
+use std::collections::HashMap;
+
+use async_graphql::{
+    dataloader::{DataLoader, Loader},
+    http::GraphiQLSource,
+    Context, EmptyMutation, EmptySubscription, Enum, InputObject, Object,
+    Request as GraphQLRequest, Response as GraphQLResponse, Result, Schema, SimpleObject, ID,
+};
+use chrono::{DateTime, Utc};
+use mongodb::{bson::doc, error::Error as MongoError, Collection};
+use poem::{
+    get, handler,
+    web::{Data, Html, Json},
+    Endpoint, EndpointExt, Route,
+};
+use timetable::{
+    filter::TimetableFilter, group::GroupCode, kind::EntryKind, location::Location,
+    merge::merge_entries, person::Person, timetable::TimeTableEntry,
+};
+
+use crate::{
+    dates::RelativeRange,
+    page::{find_pages, PageCursor, PageRequest, DEFAULT_PAGE_SIZE},
+    query::{find_groups, find_rooms, find_subjects, find_tutor, find_tutors},
+    MERGE_MAX_GAP_MINUTES,
+};
+
+/// Path the GraphQL endpoint is nested under
+pub(crate) const GRAPHQL_ROOT: &str = "/graphql";
+
+/// Deepest nesting of fields a query may have
+const MAX_DEPTH: usize = 10;
+/// Highest complexity a query may have, every field counts as 1 and a page of entries as many
+/// times as the entries asked for
+const MAX_COMPLEXITY: usize = 10_000;
+/// Lists of every group, tutor, room or subject are assumed to be this many times as expensive
+/// as a single one, so they can't be combined with entries of each of them
+const CATALOGUE_COMPLEXITY: usize = 100;
+
+pub(crate) type TimetableSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// GraphQL endpoint, `POST` runs a query and `GET` serves GraphiQL
+pub(crate) fn graphql(coll_db: Collection<TimeTableEntry>) -> impl Endpoint {
+    let schema = Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(DataLoader::new(EntryPages(coll_db.clone()), tokio::spawn))
+        .data(coll_db)
+        .limit_depth(MAX_DEPTH)
+        .limit_complexity(MAX_COMPLEXITY)
+        .finish();
+    Route::new()
+        .at("/", get(graphiql).post(execute))
+        .data(schema)
+}
+
+#[handler]
+fn graphiql() -> Html<String> {
+    Html(GraphiQLSource::build().endpoint(GRAPHQL_ROOT).finish())
+}
+
+#[handler]
+async fn execute(
+    schema: Data<&TimetableSchema>,
+    request: Json<GraphQLRequest>,
+) -> Json<GraphQLResponse> {
+    Json(schema.execute(request.0).await)
+}
+
+/// Mode of studies, encoded by the letter following the faculty
+#[derive(Clone, Copy, PartialEq, Eq, Enum)]
+#[graphql(name = "StudyMode", remote = "timetable::group::StudyMode")]
+enum StudyModeValue {
+    /// `s` - stacjonarne
+    Stationary,
+    /// `n` - niestacjonarne
+    PartTime,
+    /// `i` - internetowe
+    Remote,
+}
+
+/// How entries have to match a list of groups or tutors
+#[derive(Clone, Copy, PartialEq, Eq, Enum)]
+#[graphql(name = "ListMatch", remote = "timetable::filter::ListMatch")]
+enum ListMatchValue {
+    /// Entries of any of them
+    Any,
+    /// Only entries shared by all of them
+    All,
+}
+
+/// How entries have to fit into the date range
+#[derive(Clone, Copy, PartialEq, Eq, Enum)]
+#[graphql(name = "RangeMode", remote = "timetable::filter::RangeMode")]
+enum RangeModeValue {
+    /// Entries taking place at any moment of the range, including those spanning its ends
+    Overlap,
+    /// Only entries beginning and ending within the range
+    Contained,
+}
+
+/// Range relative to the current day in Europe/Warsaw
+#[derive(Clone, Copy, PartialEq, Eq, Enum)]
+#[graphql(name = "RelativeRange", remote = "crate::dates::RelativeRange")]
+enum RelativeRangeValue {
+    Today,
+    Tomorrow,
+    /// Monday to Sunday
+    ThisWeek,
+    NextWeek,
+    ThisMonth,
+    /// From now until the end of the semester, winter ones end with February and summer ones with September
+    RestOfSemester,
+}
+
+/// Kind of entry, parsed from its Polish label
+#[derive(Clone, Copy, PartialEq, Eq, Enum)]
+#[graphql(name = "EntryKind")]
+enum EntryKindValue {
+    Lecture,
+    Exercises,
+    Laboratory,
+    LanguageCourse,
+    Seminar,
+    Project,
+    Exam,
+    Consultation,
+    Meeting,
+    Reservation,
+    Unknown,
+}
+
+impl From<&EntryKind> for EntryKindValue {
+    fn from(kind: &EntryKind) -> Self {
+        match kind {
+            EntryKind::Lecture => Self::Lecture,
+            EntryKind::Exercises => Self::Exercises,
+            EntryKind::Laboratory => Self::Laboratory,
+            EntryKind::LanguageCourse => Self::LanguageCourse,
+            EntryKind::Seminar => Self::Seminar,
+            EntryKind::Project => Self::Project,
+            EntryKind::Exam => Self::Exam,
+            EntryKind::Consultation => Self::Consultation,
+            EntryKind::Meeting => Self::Meeting,
+            EntryKind::Reservation => Self::Reservation,
+            EntryKind::Unknown(_) => Self::Unknown,
+        }
+    }
+}
+
+impl From<EntryKindValue> for EntryKind {
+    fn from(kind: EntryKindValue) -> Self {
+        match kind {
+            EntryKindValue::Lecture => Self::Lecture,
+            EntryKindValue::Exercises => Self::Exercises,
+            EntryKindValue::Laboratory => Self::Laboratory,
+            EntryKindValue::LanguageCourse => Self::LanguageCourse,
+            EntryKindValue::Seminar => Self::Seminar,
+            EntryKindValue::Project => Self::Project,
+            EntryKindValue::Exam => Self::Exam,
+            EntryKindValue::Consultation => Self::Consultation,
+            EntryKindValue::Meeting => Self::Meeting,
+            EntryKindValue::Reservation => Self::Reservation,
+            EntryKindValue::Unknown => Self::Unknown(String::new()),
+        }
+    }
+}
+
+/// Filters of `get_timetable`.
+///
+/// Datetimes carry their offset here, so there is no `tz` to read them in. Lists are alternatives
+/// to each other, as `;` separated values are in the REST API.
+#[derive(Default, InputObject)]
+struct EntryFilter {
+    /// Beginning of search
+    date_from: Option<DateTime<Utc>>,
+    /// End of search
+    date_to: Option<DateTime<Utc>>,
+    /// Search range relative to the current day in Europe/Warsaw, instead of `dateFrom` and `dateTo`
+    when: Option<RelativeRangeValue>,
+    /// Whether entries have to overlap the search range, the default, or be contained in it
+    range_mode: Option<RangeModeValue>,
+    /// Groups to only search for
+    groups: Option<Vec<String>>,
+    /// Tutors to only search for, any spelling or id of a tutor
+    tutors: Option<Vec<String>>,
+    /// Subject names to only search for
+    subjects: Option<Vec<String>>,
+    /// Subject codes to only search for, e.g. `SOP`
+    subject_codes: Option<Vec<String>>,
+    /// Rooms to only search for, e.g. `B/227`
+    rooms: Option<Vec<String>>,
+    /// Buildings to only search for
+    buildings: Option<Vec<String>>,
+    /// Whether entries of any of the groups and tutors are returned or only those shared by all of them
+    #[graphql(name = "match")]
+    list_match: Option<ListMatchValue>,
+    /// Only entries with more than this many enrolled students
+    min_students: Option<u32>,
+    /// Only groups of this faculty, e.g. `WI`
+    faculty: Option<String>,
+    /// Only groups of this mode of studies
+    study_mode: Option<StudyModeValue>,
+    /// Only groups of this degree level - `1` or `2`
+    degree: Option<u8>,
+    /// Only groups of this semester
+    semester: Option<u8>,
+    /// Only groups with this number
+    group_number: Option<u32>,
+    /// Only entries of this kind
+    #[graphql(name = "type")]
+    type_of: Option<EntryKindValue>,
+    /// Only remote (`true`) or only on-site (`false`) entries
+    remote: Option<bool>,
+    /// Leave out entries announced as cancelled
+    hide_cancelled: Option<bool>,
+    /// Merge back-to-back blocks of the same class into one entry
+    merge: Option<bool>,
+}
+
+impl EntryFilter {
+    fn filter(&self) -> Result<TimetableFilter> {
+        let mut filter = TimetableFilter::new()
+            .groups(self.groups.iter().flatten())
+            .tutors(self.tutors.iter().flatten())
+            .subjects(self.subjects.iter().flatten())
+            .subject_codes(self.subject_codes.iter().flatten())
+            .rooms(self.rooms.iter().flatten())
+            .buildings(self.buildings.iter().flatten())
+            .kinds(self.type_of.map(EntryKind::from))
+            .list_match(self.list_match.map(Into::into).unwrap_or_default())
+            .range_mode(self.range_mode.map(Into::into).unwrap_or_default())
+            .hide_cancelled(self.hide_cancelled.unwrap_or(false));
+        let (date_from, date_to) = match self.when {
+            Some(_) if self.date_from.is_some() || self.date_to.is_some() => {
+                return Err("`when` can't be combined with `dateFrom` or `dateTo`".into())
+            }
+            Some(when) => {
+                let (date_from, date_to) = RelativeRange::from(when)
+                    .resolve(Utc::now())
+                    .ok_or("`when` is out of range")?;
+                (Some(date_from), Some(date_to))
+            }
+            None => (self.date_from, self.date_to),
+        };
+        if let Some(date_from) = date_from {
+            filter = filter.date_from(date_from);
+        }
+        if let Some(date_to) = date_to {
+            filter = filter.date_to(date_to);
+        }
+        if let Some(min_students) = self.min_students {
+            filter = filter.min_students(min_students);
+        }
+        if let Some(faculty) = &self.faculty {
+            filter = filter.faculty(faculty);
+        }
+        if let Some(study_mode) = self.study_mode {
+            filter = filter.study_mode(study_mode.into());
+        }
+        if let Some(degree) = self.degree {
+            filter = filter.degree(degree);
+        }
+        if let Some(semester) = self.semester {
+            filter = filter.semester(semester);
+        }
+        if let Some(group_number) = self.group_number {
+            filter = filter.group_number(group_number);
+        }
+        if let Some(remote) = self.remote {
+            filter = filter.remote(remote);
+        }
+        Ok(filter)
+    }
+
+    /// Whether groups and tutors are all required
+    fn all(&self) -> bool {
+        self.list_match == Some(ListMatchValue::All)
+    }
+}
+
+/// Values of a list of the filter narrowed down to `value` of the parent object, `None` when the
+/// list leaves the parent out.
+///
+/// Lists of alternatives keep `value` if any of their values is `same` as it, lists required
+/// `all` at once get it added.
+fn scoped(
+    values: Option<Vec<String>>,
+    value: &str,
+    all: bool,
+    same: impl Fn(&str) -> bool,
+) -> Option<Vec<String>> {
+    match values {
+        Some(mut values) if all => {
+            values.push(value.to_string());
+            Some(values)
+        }
+        Some(values) if !values.is_empty() && !values.iter().any(|other| same(other)) => None,
+        _ => Some(vec![value.to_string()]),
+    }
+}
+
+/// Loads the pages of every `entries` field resolved at once in a single query, e.g. those of
+/// each tutor of `tutors { entries { ... } }`
+struct EntryPages(Collection<TimeTableEntry>);
+
+impl Loader<PageRequest> for EntryPages {
+    type Value = (Vec<TimeTableEntry>, Option<PageCursor>);
+    type Error = MongoError;
+
+    async fn load(
+        &self,
+        requests: &[PageRequest],
+    ) -> Result<HashMap<PageRequest, Self::Value>, Self::Error> {
+        let pages = find_pages(&self.0, requests).await?;
+        Ok(requests.iter().cloned().zip(pages).collect())
+    }
+}
+
+/// Page of entries matching the filter, `scope` narrows it down to those of the parent object
+/// and gives `None` when the filter leaves the parent out
+async fn find_entries(
+    ctx: &Context<'_>,
+    filter: Option<EntryFilter>,
+    first: Option<u32>,
+    after: Option<String>,
+    scope: impl FnOnce(EntryFilter) -> Option<EntryFilter>,
+) -> Result<EntryPage> {
+    let after = match after.as_deref().map(PageCursor::parse) {
+        Some(None) => return Err("Cursors have to be taken from `next`".into()),
+        after => after.flatten(),
+    };
+    let filter = match scope(filter.unwrap_or_default()) {
+        Some(filter) => filter,
+        None => return Ok(EntryPage::default()),
+    };
+    let merge_gap = filter
+        .merge
+        .unwrap_or(false)
+        .then(|| chrono::Duration::minutes(MERGE_MAX_GAP_MINUTES));
+    let request = PageRequest {
+        filter: filter.filter()?,
+        limit: first.unwrap_or(DEFAULT_PAGE_SIZE),
+        after,
+        merge_gap,
+    };
+    let (mut entries, next) = ctx
+        .data::<DataLoader<EntryPages>>()?
+        .load_one(request)
+        .await?
+        .unwrap_or_default();
+    if let Some(merge_gap) = merge_gap {
+        entries = merge_entries(entries, merge_gap);
+    }
+    entries.sort_by_key(|entry| entry.get_datetime_beginning());
+    Ok(EntryPage {
+        nodes: entries.into_iter().map(Entry).collect(),
+        next: next.map(|next| next.to_string()),
+    })
+}
+
+/// Entries sorted by beginning, `first` at a time
+#[derive(Default, SimpleObject)]
+struct EntryPage {
+    /// Entries of the page
+    nodes: Vec<Entry>,
+    /// Cursor of the following page to give as `after`, `null` on the last page
+    next: Option<String>,
+}
+
+pub(crate) struct QueryRoot;
+
+#[Object(name = "Query")]
+impl QueryRoot {
+    /// Entries matching the filters of `get_timetable`
+    #[graphql(complexity = "first.unwrap_or(DEFAULT_PAGE_SIZE) as usize * child_complexity")]
+    async fn entries(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<EntryFilter>,
+        #[graphql(validator(minimum = 1, maximum = 2000))] first: Option<u32>,
+        after: Option<String>,
+    ) -> Result<EntryPage> {
+        find_entries(ctx, filter, first, after, Some).await
+    }
+    /// Single entry by its id
+    async fn entry(&self, ctx: &Context<'_>, id: ID) -> Result<Option<Entry>> {
+        let coll_db = ctx.data::<Collection<TimeTableEntry>>()?;
+        Ok(coll_db
+            .find_one(doc! {"_id": id.as_str()}, None)
+            .await?
+            .map(Entry))
+    }
+    /// Every group with an entry
+    #[graphql(complexity = "CATALOGUE_COMPLEXITY * child_complexity")]
+    async fn groups(&self, ctx: &Context<'_>) -> Result<Vec<Group>> {
+        let coll_db = ctx.data::<Collection<TimeTableEntry>>()?;
+        Ok(find_groups(coll_db)
+            .await?
+            .iter()
+            .map(|group| Group::new(group))
+            .collect())
+    }
+    /// Group by its name, in any spelling
+    async fn group(&self, name: String) -> Group {
+        Group::new(&name)
+    }
+    /// Every tutor with an entry
+    #[graphql(complexity = "CATALOGUE_COMPLEXITY * child_complexity")]
+    async fn tutors(&self, ctx: &Context<'_>) -> Result<Vec<Tutor>> {
+        let coll_db = ctx.data::<Collection<TimeTableEntry>>()?;
+        Ok(find_tutors(coll_db)
+            .await?
+            .into_iter()
+            .map(|tutor| Tutor {
+                person: tutor.person().clone(),
+                aliases: tutor.aliases().to_vec(),
+            })
+            .collect())
+    }
+    /// Tutor by any spelling of their name or by id
+    async fn tutor(&self, ctx: &Context<'_>, id: String) -> Result<Option<Tutor>> {
+        let coll_db = ctx.data::<Collection<TimeTableEntry>>()?;
+        Ok(find_tutor(coll_db, &Person::id_of(&id))
+            .await?
+            .map(|tutor| Tutor {
+                person: tutor.person().clone(),
+                aliases: tutor.aliases().to_vec(),
+            }))
+    }
+    /// Every room with an entry
+    #[graphql(complexity = "CATALOGUE_COMPLEXITY * child_complexity")]
+    async fn rooms(&self, ctx: &Context<'_>) -> Result<Vec<Room>> {
+        let coll_db = ctx.data::<Collection<TimeTableEntry>>()?;
+        Ok(find_rooms(coll_db)
+            .await?
+            .into_iter()
+            .map(|(building, room)| Room::new(building, room))
+            .collect())
+    }
+    /// Every subject with an entry
+    #[graphql(complexity = "CATALOGUE_COMPLEXITY * child_complexity")]
+    async fn subjects(&self, ctx: &Context<'_>) -> Result<Vec<Subject>> {
+        let coll_db = ctx.data::<Collection<TimeTableEntry>>()?;
+        Ok(find_subjects(coll_db)
+            .await?
+            .into_iter()
+            .map(|(name, code)| Subject { name, code })
+            .collect())
+    }
+}
+
+/// Class or reservation
+struct Entry(TimeTableEntry);
+
+#[Object]
+impl Entry {
+    /// Stable id of entry
+    async fn id(&self) -> ID {
+        ID::from(self.0.get_id())
+    }
+    /// Title of reservation
+    async fn title(&self) -> Option<&str> {
+        self.0.get_title()
+    }
+    /// Subjects, along with their codes when known
+    async fn subjects(&self) -> Vec<Subject> {
+        self.0
+            .get_subjects()
+            .iter()
+            .enumerate()
+            .map(|(index, name)| Subject {
+                name: name.clone(),
+                code: self.0.get_subject_codes().get(index).cloned(),
+            })
+            .collect()
+    }
+    /// Type of entry as shown in the plan, e.g. `Wykład`
+    async fn type_of(&self) -> &str {
+        self.0.get_type_of()
+    }
+    /// Kind of entry
+    async fn kind(&self) -> EntryKindValue {
+        self.0.get_kind().into()
+    }
+    /// Tutors or person who made a reservation
+    async fn tutors(&self) -> Vec<Tutor> {
+        self.0
+            .get_tutors()
+            .iter()
+            .map(|person| Tutor {
+                person: person.clone(),
+                aliases: self
+                    .0
+                    .get_persons()
+                    .iter()
+                    .filter(|raw| Person::id_of(raw) == person.id())
+                    .cloned()
+                    .collect(),
+            })
+            .collect()
+    }
+    /// Groups attending
+    async fn groups(&self) -> Vec<Group> {
+        self.0
+            .get_groups()
+            .unwrap_or_default()
+            .iter()
+            .map(|group| Group::new(group))
+            .collect()
+    }
+    /// Room of entry
+    async fn room(&self) -> Room {
+        Room::new(
+            self.0.get_building().to_string(),
+            self.0.get_room().to_string(),
+        )
+    }
+    /// Date and time of beginning
+    async fn datetime_beginning(&self) -> DateTime<Utc> {
+        self.0.get_datetime_beginning()
+    }
+    /// Date and time of ending
+    async fn datetime_ending(&self) -> DateTime<Utc> {
+        self.0.get_datetime_ending()
+    }
+    /// Number of enrolled students
+    async fn students_enrolled(&self) -> Option<u32> {
+        self.0
+            .get_students_count()
+            .and_then(|students| students.enrolled())
+    }
+    /// Limit of students
+    async fn students_limit(&self) -> Option<u32> {
+        self.0
+            .get_students_count()
+            .and_then(|students| students.limit())
+    }
+    /// Free text of entry
+    async fn details(&self) -> Option<&str> {
+        self.0.get_details()
+    }
+    /// Changes announced in the free text
+    async fn status(&self) -> Status {
+        let status = self.0.get_status();
+        Status {
+            cancelled: status.cancelled(),
+            moved: status.moved(),
+            online_only: status.online_only(),
+            substitute: status.substitute(),
+        }
+    }
+}
+
+/// Changes announced in the free text of an entry
+#[derive(SimpleObject)]
+#[graphql(name = "EntryStatus")]
+struct Status {
+    /// Class doesn't take place
+    cancelled: bool,
+    /// Class was moved to another date, time or room
+    moved: bool,
+    /// Class takes place only online
+    online_only: bool,
+    /// Class is led by a substitute tutor
+    substitute: bool,
+}
+
+/// Student group, e.g. `WIs I.2 - 46c`
+struct Group {
+    name: String,
+    code: Option<GroupCode>,
+}
+
+impl Group {
+    fn new(name: &str) -> Self {
+        Self {
+            name: GroupCode::normalise(name),
+            code: GroupCode::parse(name),
+        }
+    }
+}
+
+#[Object]
+impl Group {
+    /// Normalised name of the group
+    async fn name(&self) -> &str {
+        &self.name
+    }
+    /// Faculty, e.g. `WI`
+    async fn faculty(&self) -> Option<&str> {
+        self.code.as_ref().map(GroupCode::faculty)
+    }
+    /// Mode of studies
+    async fn study_mode(&self) -> Option<StudyModeValue> {
+        self.code
+            .as_ref()
+            .and_then(GroupCode::study_mode)
+            .map(StudyModeValue::from)
+    }
+    /// Degree level, `1` for `I`, `2` for `II`
+    async fn degree(&self) -> Option<u8> {
+        self.code.as_ref().map(GroupCode::degree)
+    }
+    /// Semester
+    async fn semester(&self) -> Option<u8> {
+        self.code.as_ref().map(GroupCode::semester)
+    }
+    /// Number of the group
+    async fn number(&self) -> Option<u32> {
+        self.code.as_ref().map(GroupCode::number)
+    }
+    /// Entries of the group matching the filter
+    #[graphql(complexity = "first.unwrap_or(DEFAULT_PAGE_SIZE) as usize * child_complexity")]
+    async fn entries(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<EntryFilter>,
+        #[graphql(validator(minimum = 1, maximum = 2000))] first: Option<u32>,
+        after: Option<String>,
+    ) -> Result<EntryPage> {
+        find_entries(ctx, filter, first, after, |mut filter| {
+            let all = filter.all();
+            filter.groups = Some(scoped(filter.groups.take(), &self.name, all, |group| {
+                GroupCode::normalise(group) == self.name
+            })?);
+            Some(filter)
+        })
+        .await
+    }
+}
+
+/// Tutor along with every spelling of their name
+struct Tutor {
+    person: Person,
+    aliases: Vec<String>,
+}
+
+#[Object]
+impl Tutor {
+    /// Stable id, the same for every ordering of names and with or without titles
+    async fn id(&self) -> ID {
+        ID::from(self.person.id())
+    }
+    /// Titles, surname and given names
+    async fn name(&self) -> String {
+        self.person
+            .titles()
+            .iter()
+            .map(String::as_str)
+            .chain([self.person.surname()])
+            .chain(self.person.given_names().iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+    /// Surname
+    async fn surname(&self) -> &str {
+        self.person.surname()
+    }
+    /// Given names
+    async fn given_names(&self) -> &[String] {
+        self.person.given_names()
+    }
+    /// Academic titles
+    async fn titles(&self) -> &[String] {
+        self.person.titles()
+    }
+    /// Every spelling of the name
+    async fn aliases(&self) -> &[String] {
+        &self.aliases
+    }
+    /// Entries of the tutor matching the filter
+    #[graphql(complexity = "first.unwrap_or(DEFAULT_PAGE_SIZE) as usize * child_complexity")]
+    async fn entries(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<EntryFilter>,
+        #[graphql(validator(minimum = 1, maximum = 2000))] first: Option<u32>,
+        after: Option<String>,
+    ) -> Result<EntryPage> {
+        find_entries(ctx, filter, first, after, |mut filter| {
+            let id = self.person.id();
+            let all = filter.all();
+            filter.tutors = Some(scoped(filter.tutors.take(), id, all, |tutor| {
+                Person::id_of(tutor) == id
+            })?);
+            Some(filter)
+        })
+        .await
+    }
+}
+
+/// Room in a building, or where a remote entry takes place
+struct Room {
+    building: String,
+    room: String,
+    location: Location,
+}
+
+impl Room {
+    fn new(building: String, room: String) -> Self {
+        Self {
+            location: Location::parse(&building, &room),
+            building,
+            room,
+        }
+    }
+}
+
+#[Object]
+impl Room {
+    /// Building as shown in the plan
+    async fn building(&self) -> &str {
+        &self.building
+    }
+    /// Room as shown in the plan
+    async fn name(&self) -> &str {
+        &self.room
+    }
+    /// Wing, e.g. `B` of `B/227`
+    async fn wing(&self) -> Option<&str> {
+        self.location.wing()
+    }
+    /// Floor, e.g. `2` of `B/227`
+    async fn floor(&self) -> Option<i32> {
+        self.location.floor()
+    }
+    /// Number of the room, e.g. `227` of `B/227`
+    async fn number(&self) -> Option<&str> {
+        self.location.room_number()
+    }
+    /// Whether the entry doesn't take place in a physical room
+    async fn remote(&self) -> bool {
+        self.location.is_remote()
+    }
+    /// Entries in the room matching the filter
+    #[graphql(complexity = "first.unwrap_or(DEFAULT_PAGE_SIZE) as usize * child_complexity")]
+    async fn entries(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<EntryFilter>,
+        #[graphql(validator(minimum = 1, maximum = 2000))] first: Option<u32>,
+        after: Option<String>,
+    ) -> Result<EntryPage> {
+        find_entries(ctx, filter, first, after, |mut filter| {
+            filter.buildings = Some(scoped(
+                filter.buildings.take(),
+                &self.building,
+                false,
+                |building| building == self.building,
+            )?);
+            filter.rooms = Some(scoped(filter.rooms.take(), &self.room, false, |room| {
+                room == self.room
+            })?);
+            Some(filter)
+        })
+        .await
+    }
+}
+
+/// Subject, e.g. `Systemy operacyjne`
+struct Subject {
+    name: String,
+    code: Option<String>,
+}
+
+#[Object]
+impl Subject {
+    /// Name of the subject
+    async fn name(&self) -> &str {
+        &self.name
+    }
+    /// Code of the subject, e.g. `SOP`
+    async fn code(&self) -> Option<&str> {
+        self.code.as_deref()
+    }
+    /// Entries of the subject matching the filter
+    #[graphql(complexity = "first.unwrap_or(DEFAULT_PAGE_SIZE) as usize * child_complexity")]
+    async fn entries(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<EntryFilter>,
+        #[graphql(validator(minimum = 1, maximum = 2000))] first: Option<u32>,
+        after: Option<String>,
+    ) -> Result<EntryPage> {
+        find_entries(ctx, filter, first, after, |mut filter| {
+            filter.subjects = Some(scoped(
+                filter.subjects.take(),
+                &self.name,
+                false,
+                |subject| subject == self.name,
+            )?);
+            Some(filter)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(values: &[&str]) -> Option<Vec<String>> {
+        Some(values.iter().map(|value| value.to_string()).collect())
+    }
+
+    #[test]
+    fn scope_without_values_is_the_parent() {
+        assert_eq!(scoped(None, "B/227", false, |_| true), list(&["B/227"]));
+        assert_eq!(
+            scoped(list(&[]), "B/227", false, |_| true),
+            list(&["B/227"])
+        );
+    }
+
+    #[test]
+    fn scope_keeps_parent_among_alternatives() {
+        let scope = scoped(list(&["A/152", "B/227"]), "B/227", false, |room| {
+            room == "B/227"
+        });
+        assert_eq!(scope, list(&["B/227"]));
+    }
+
+    #[test]
+    fn scope_leaves_parent_out_of_other_alternatives() {
+        let scope = scoped(list(&["A/152"]), "B/227", false, |room| room == "B/227");
+        assert_eq!(scope, None);
+    }
+
+    #[test]
+    fn scope_adds_parent_to_values_required_together() {
+        let scope = scoped(list(&["WIs I.2 - 23c"]), "WIs I.2 - 46c", true, |_| false);
+        assert_eq!(scope, list(&["WIs I.2 - 23c", "WIs I.2 - 46c"]));
+    }
+}
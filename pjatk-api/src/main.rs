@@ -2,31 +2,53 @@
 
 use api_utils::SigmaApiData;
 use api_utils::SigmaApiError;
+use api_utils::SigmaApiLinks;
+use api_utils::SigmaApiRange;
 use api_utils::SigmaApiResponse;
-use futures::stream::TryStreamExt;
-use mongodb::bson::{Bson, DateTime};
+use chrono::Utc;
 
 use poem::middleware::TowerLayerCompatExt;
 use poem::EndpointExt;
 
 use serde::Deserialize;
-use timetable::timetable::TimeTableEntry;
+use timetable::{
+    ics::{entries_calendar, series_calendar},
+    merge::merge_entries,
+    migration::{migrate_collection, SCHEMA_VERSION},
+    person::Tutor,
+    series::{detect_series, Series},
+    timetable::TimeTableEntry,
+};
 
-use mongodb::{bson::doc, Collection, Cursor};
+use mongodb::{bson::doc, Collection};
 
-use poem::{listener::TcpListener, web::Data, Route, Server};
+use poem::{listener::TcpListener, web::Data, Request, Route, Server};
 use poem_openapi::param::Query;
-use poem_openapi::{payload::Json, OpenApi, OpenApiService};
+use poem_openapi::{
+    payload::{Binary, Json, PlainText},
+    ApiResponse, OpenApi, OpenApiService,
+};
 
+use caldav::{caldav, well_known_caldav, CALDAV_ROOT};
 use config::Config;
+use export::{parse_columns, to_csv, to_xlsx, Column};
+use graphql::{graphql, GRAPHQL_ROOT};
+use page::{find_all, find_page, page_link, PageCursor, DEFAULT_PAGE_SIZE, MAX_UNPAGED_ENTRIES};
+use query::{find_groups, find_tutors, timetable_endpoints, TimetableQuery};
 use std::error::Error as StdError;
 
 use std::ops::Deref;
 use std::time::Duration;
-use tracing::{error, Level};
+use tracing::{error, info, Level};
 use tracing_subscriber::FmtSubscriber;
 
+mod caldav;
 mod config;
+mod dates;
+mod export;
+mod graphql;
+mod page;
+mod query;
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn StdError>> {
     let config = Config::new().await?;
@@ -35,15 +57,38 @@ async fn main() -> Result<(), Box<dyn StdError>> {
         .finish();
     tracing::subscriber::set_global_default(subscriber)?;
     let coll_db = config.get_collection().await?;
+    let report = migrate_collection(&coll_db.clone_with_type()).await?;
+    info!(
+        "Migrated {} documents, {} under a new id",
+        report.migrated + report.rekeyed,
+        report.rekeyed
+    );
+    // Requests don't check the schema, so serving documents left at an older version is not an
+    // option
+    if !report.failed.is_empty() {
+        for failure in &report.failed {
+            error!("{}", failure);
+        }
+        return Err(format!(
+            "{} documents could not be migrated to schema version {}",
+            report.failed.len(),
+            SCHEMA_VERSION
+        )
+        .into());
+    }
     let port = config.get_port();
     let server_url = config.get_complete_server_url();
-    let api_service = OpenApiService::new(Api, "PJATK Schedule API", "0.4.3").server(server_url);
+    let api_service = OpenApiService::new((TimetableApi, Api), "PJATK Schedule API", "0.4.3")
+        .server(server_url);
     let docs = api_service.redoc();
     let open_api_specs = api_service.spec_endpoint();
     let app = Route::new()
         .nest("/", docs)
         .nest("/api", api_service)
         .nest("/openapi.json", open_api_specs)
+        .nest(CALDAV_ROOT, caldav())
+        .nest(GRAPHQL_ROOT, graphql(coll_db.clone()))
+        .at("/.well-known/caldav", well_known_caldav)
         .data(coll_db.clone())
         .with(tower::limit::RateLimitLayer::new(5, Duration::from_secs(1)).compat())
         .with(poem::middleware::Tracing)
@@ -62,120 +107,330 @@ struct ApiParams {
     groups: Option<String>,
 }
 
-struct Api;
-#[OpenApi]
-impl Api {
-    /// Get an timetable
-    #[oai(path = "/get_timetable", method = "get")]
-    async fn get_timetable(
-        &self,
-        coll_db: Data<&Collection<TimeTableEntry>>,
-        /// Unix timestamp - beginning of search
-        date_from: Query<Option<i64>>,
-        /// Unix timestamp - end of search
-        date_to: Query<Option<i64>>,
-        /// Array of groups to only search for - seperated by `;`
-        groups: Query<Option<String>>,
-        /// Array of tutors to only search for - seperated by `;`
-        tutors: Query<Option<String>>,
-    ) -> SigmaApiResponse<Vec<TimeTableEntry>, SigmaApiError> {
-        let mut entries: Vec<TimeTableEntry> = vec![];
-        if let Some(groups) = groups.deref() {
-            for group in groups
-                .split_terminator(';')
-                .filter(|group| !group.is_empty())
+/// Longest break between two blocks of a class that still get merged
+const MERGE_MAX_GAP_MINUTES: i64 = 15;
+
+#[derive(ApiResponse)]
+enum CalendarResponse {
+    /// iCalendar feed, empty when nothing was found
+    #[oai(status = 200, content_type = "text/calendar; charset=utf-8")]
+    Calendar(PlainText<String>),
+    /// User send out bad request
+    #[oai(status = 400)]
+    BadRequest(Json<SigmaApiError>),
+    /// Server encountered internal error
+    #[oai(status = 500)]
+    InternalError(Json<SigmaApiError>),
+}
+
+#[derive(ApiResponse)]
+enum CsvResponse {
+    /// CSV file, only a header row when nothing was found
+    #[oai(status = 200, content_type = "text/csv; charset=utf-8")]
+    Csv(
+        PlainText<String>,
+        #[oai(header = "Content-Disposition")] String,
+    ),
+    /// User send out bad request
+    #[oai(status = 400)]
+    BadRequest(Json<SigmaApiError>),
+    /// Server encountered internal error
+    #[oai(status = 500)]
+    InternalError(Json<SigmaApiError>),
+}
+
+#[derive(ApiResponse)]
+enum XlsxResponse {
+    /// XLSX workbook, only a header row when nothing was found
+    #[oai(
+        status = 200,
+        content_type = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+    )]
+    Xlsx(Binary<Vec<u8>>, #[oai(header = "Content-Disposition")] String),
+    /// User send out bad request
+    #[oai(status = 400)]
+    BadRequest(Json<SigmaApiError>),
+    /// Server encountered internal error
+    #[oai(status = 500)]
+    InternalError(Json<SigmaApiError>),
+}
+
+/// Response given instead of an export
+enum ExportError {
+    BadRequest(Json<SigmaApiError>),
+    InternalError(Json<SigmaApiError>),
+}
+
+impl From<ExportError> for CsvResponse {
+    fn from(error: ExportError) -> Self {
+        match error {
+            ExportError::BadRequest(error) => Self::BadRequest(error),
+            ExportError::InternalError(error) => Self::InternalError(error),
+        }
+    }
+}
+
+impl From<ExportError> for XlsxResponse {
+    fn from(error: ExportError) -> Self {
+        match error {
+            ExportError::BadRequest(error) => Self::BadRequest(error),
+            ExportError::InternalError(error) => Self::InternalError(error),
+        }
+    }
+}
+
+struct TimetableApi;
+
+timetable_endpoints! {
+    impl TimetableApi {
+        /// Get an timetable
+        ///
+        /// Every filter given has to match, e.g. `groups` and `tutors` together give the classes of those tutors with those groups. Values of a list are alternatives.
+        ///
+        /// Entries come in pages of `limit`, the `next` link of `links` leads to the following one. With `merge`, pages never split a merged entry, so they may be a little shorter or longer than `limit`.
+        #[oai(path = "/get_timetable", method = "get")]
+        async fn get_timetable(
+            &self,
+            query: TimetableQuery,
+            req: &Request,
+            coll_db: Data<&Collection<TimeTableEntry>>,
+            /// Merge back-to-back blocks of the same class into one entry, listing the blocks in `parts`
+            merge: Query<Option<bool>>,
+            /// Most entries to return at once, the rest is behind the `next` link
+            #[oai(validator(minimum(value = "1"), maximum(value = "2000")))]
+            limit: Query<Option<u32>>,
+            /// Opaque position of a page, taken from a `next` link
+            cursor: Query<Option<String>>,
+        ) -> SigmaApiResponse<Vec<TimeTableEntry>, SigmaApiError> {
+            let after = match cursor.0.as_deref().map(PageCursor::parse) {
+                Some(None) => {
+                    error!("{}", "Invalid cursor!");
+                    return SigmaApiResponse::BadRequest(Json(
+                        SigmaApiError::error(
+                            400,
+                            "Invalid cursor!".to_string(),
+                            Some("Cursors have to be taken from `next` links".to_string()),
+                        )
+                        .expect("Error failed!"),
+                    ));
+                }
+                after => after.flatten(),
+            };
+            let filter = match query.filter() {
+                Ok(filter) => filter,
+                Err(cause) => return SigmaApiResponse::BadRequest(invalid_query(cause)),
+            };
+            let merge_gap = merge
+                .unwrap_or(false)
+                .then(|| chrono::Duration::minutes(MERGE_MAX_GAP_MINUTES));
+            let (mut entries, next) = match find_page(
+                &coll_db,
+                &filter,
+                limit.unwrap_or(DEFAULT_PAGE_SIZE),
+                after.as_ref(),
+                merge_gap,
+            )
+            .await
             {
-                let cursor: Cursor<TimeTableEntry> = coll_db
-                .find(
-                    match (date_from.deref(), date_to.deref()) {
-                        (Some(date_from), Some(date_to)) => {
-                            let datetime_beginning= DateTime::from_millis(*date_from * 1000);
-                            let datetime_ending= DateTime::from_millis(*date_to * 1000);
-                            doc! {"datetime_beginning":{"$gte":Bson::DateTime(datetime_beginning)},"datetime_ending":{"$lte":Bson::DateTime(datetime_ending)},"groups":group}
-                        },
-                        (Some(date_from), None) => {
-                            let datetime_beginning= DateTime::from_millis(*date_from * 1000);
-                            doc! {"datetime_beginning":{"$gte":Bson::DateTime(datetime_beginning)},"groups":group}
-                        },
-                        (None, Some(date_to)) => {
-                            let datetime_ending= DateTime::from_millis(*date_to * 1000);
-                            doc! {"datetime_ending":{"$lte":Bson::DateTime(datetime_ending)},"groups":group}
-                        },
-                        (None, None) => doc! {"groups":group},
-                    },
-                    None,
-                )
-                .await
-                .expect("find failed!");
-                let mut group_entries: Vec<TimeTableEntry> =
-                    cursor.try_collect().await.expect("collect failed!");
-                entries.append(&mut group_entries);
+                Ok(page) => page,
+                Err(_) => return SigmaApiResponse::InternalError(mongo_error()),
+            };
+
+            if entries.is_empty() {
+                error!("{}", "No entries found!");
+                SigmaApiResponse::NotFound(Json(
+                    SigmaApiError::error(404, "No entries found!".to_string(), None)
+                        .expect("Error failed!"),
+                ))
+            } else {
+                if let Some(merge_gap) = merge_gap {
+                    entries = merge_entries(entries, merge_gap);
+                }
+                entries.sort_by_key(|a| a.get_datetime_beginning());
+                if let Ok(Some(timezone)) = query.timezone() {
+                    entries = entries
+                        .into_iter()
+                        .map(|entry| entry.with_local_time(timezone))
+                        .collect();
+                }
+                let next = next.map(|next| page_link(req.original_uri(), &next));
+                SigmaApiResponse::Found(Json(
+                    SigmaApiData::new(entries)
+                        .with_links(SigmaApiLinks::new(next))
+                        .with_range(SigmaApiRange::new(
+                            filter.get_date_from(),
+                            filter.get_date_to(),
+                        )),
+                ))
             }
-        } else if let Some(tutors) = tutors.deref() {
-            for tutor in tutors
-                .split_terminator(';')
-                .filter(|tutor| !tutor.is_empty())
-            {
-                let cursor: Cursor<TimeTableEntry> = coll_db
-            .find(
-                match (date_from.deref(), date_to.deref()) {
-                    (Some(date_from), Some(date_to)) => {
-                        let datetime_beginning= DateTime::from_millis(*date_from * 1000);
-                        let datetime_ending= DateTime::from_millis(*date_to * 1000);
-                        doc! {"datetime_beginning":{"$gte":Bson::DateTime(datetime_beginning)},"datetime_ending":{"$lte":Bson::DateTime(datetime_ending)},"persons":tutor}
-                    },
-                    (Some(date_from), None) => {
-                        let datetime_beginning= DateTime::from_millis(*date_from * 1000);
-                        doc! {"datetime_beginning":{"$gte":Bson::DateTime(datetime_beginning)},"persons":tutor}
-                    },
-                    (None, Some(date_to)) => {
-                        let datetime_ending= DateTime::from_millis(*date_to * 1000);
-                        doc! {"datetime_ending":{"$lte":Bson::DateTime(datetime_ending)},"persons":tutor}
-                    },
-                    (None, None) => doc! {"persons":tutor},
-                },
-                None,
+        }
+
+        /// Get classes as weekly series instead of single occurrences, takes the filters of `get_timetable`
+        #[oai(path = "/get_series", method = "get")]
+        async fn get_series(
+            &self,
+            query: TimetableQuery,
+            coll_db: Data<&Collection<TimeTableEntry>>,
+        ) -> SigmaApiResponse<Vec<Series>, SigmaApiError> {
+            let filter = match query.filter() {
+                Ok(filter) => filter,
+                Err(cause) => return SigmaApiResponse::BadRequest(invalid_query(cause)),
+            };
+            let mut series = match find_all(&coll_db, &filter).await {
+                Ok(Some(entries)) => detect_series(entries),
+                Ok(None) => return SigmaApiResponse::BadRequest(too_many_entries()),
+                Err(_) => return SigmaApiResponse::InternalError(mongo_error()),
+            };
+            if let Ok(Some(timezone)) = query.timezone() {
+                series = series
+                    .into_iter()
+                    .map(|series| series.with_local_time(timezone))
+                    .collect();
+            }
+            if series.is_empty() {
+                error!("{}", "No entries found!");
+                SigmaApiResponse::NotFound(Json(
+                    SigmaApiError::error(404, "No entries found!".to_string(), None)
+                        .expect("Error failed!"),
+                ))
+            } else {
+                SigmaApiResponse::Found(Json(SigmaApiData::new(series).with_range(
+                    SigmaApiRange::new(filter.get_date_from(), filter.get_date_to()),
+                )))
+            }
+        }
+
+        /// Get a timetable as an iCalendar feed, takes the filters of `get_timetable` and works as a webcal subscription
+        #[oai(path = "/calendar.ics", method = "get")]
+        async fn get_calendar(
+            &self,
+            query: TimetableQuery,
+            coll_db: Data<&Collection<TimeTableEntry>>,
+            /// One recurring event per series instead of one event per entry
+            series: Query<Option<bool>>,
+        ) -> CalendarResponse {
+            let name = format!(
+                "PJATK {}",
+                query
+                    .groups
+                    .as_deref()
+                    .or(query.tutors.as_deref())
+                    .unwrap_or("plan")
+                    .replace(';', ", ")
+            );
+            let filter = match query.filter() {
+                Ok(filter) => filter,
+                Err(cause) => return CalendarResponse::BadRequest(invalid_query(cause)),
+            };
+            let mut entries = match find_all(&coll_db, &filter).await {
+                Ok(Some(entries)) => entries,
+                Ok(None) => return CalendarResponse::BadRequest(too_many_entries()),
+                Err(_) => return CalendarResponse::InternalError(mongo_error()),
+            };
+            entries.sort_by_key(|a| a.get_datetime_beginning());
+            let calendar = if series.unwrap_or(false) {
+                series_calendar(&name, &detect_series(entries), Utc::now())
+            } else {
+                entries_calendar(&name, &entries, Utc::now())
+            };
+            CalendarResponse::Calendar(PlainText(calendar))
+        }
+
+        /// Get a timetable as a CSV file, takes the filters of `get_timetable`. Dates and times are in Europe/Warsaw time, values of lists are seperated by `; `
+        #[oai(path = "/export.csv", method = "get")]
+        async fn export_csv(
+            &self,
+            query: TimetableQuery,
+            coll_db: Data<&Collection<TimeTableEntry>>,
+            /// Array of columns - seperated by `;`, any of `id`, `date`, `time_beginning`, `time_ending`, `title`, `subjects`, `subject_codes`, `type_of`, `kind`, `persons`, `groups`, `building`, `room`, `students`, `status` and `details`.
+            /// Defaults to `date;time_beginning;time_ending;subjects;type_of;persons;groups;building;room`
+            columns: Query<Option<String>>,
+            /// Merge back-to-back blocks of the same class into one row
+            merge: Query<Option<bool>>,
+        ) -> CsvResponse {
+            match export_entries(
+                &coll_db,
+                columns.as_deref(),
+                query,
+                merge.unwrap_or(false),
             )
             .await
-            .expect("find failed!");
-                let mut group_entries: Vec<TimeTableEntry> =
-                    cursor.try_collect().await.expect("collect failed!");
-                entries.append(&mut group_entries);
+            {
+                Ok((columns, entries)) => CsvResponse::Csv(
+                    PlainText(to_csv(&entries, &columns)),
+                    "attachment; filename=\"plan.csv\"".to_string(),
+                ),
+                Err(error) => error.into(),
             }
-        } else {
-            let cursor: Cursor<TimeTableEntry> = coll_db
-            .find(
-                match (date_from.deref(), date_to.deref()) {
-                    (Some(date_from), Some(date_to)) => {
-                        let datetime_beginning= DateTime::from_millis(*date_from * 1000);
-                        let datetime_ending= DateTime::from_millis(*date_to * 1000);
-                        doc! {"datetime_beginning":{"$gte":Bson::DateTime(datetime_beginning)},"datetime_ending":{"$lte":Bson::DateTime(datetime_ending)}}},
-                    (Some(date_from), None) => {
-                        let datetime_beginning= DateTime::from_millis(*date_from * 1000);
-                        doc! {"datetime_beginning":{"$gte":Bson::DateTime(datetime_beginning)}}
-                    },
-                    (None, Some(date_to)) => {
-                        let datetime_ending= DateTime::from_millis(*date_to * 1000);
-                        doc! {"datetime_ending":{"$lte":Bson::DateTime(datetime_ending)}}
-                    },
-                    (None, None) => doc! {},
-                },
-                None,
+        }
+
+        /// Get a timetable as an XLSX workbook, takes the filters of `get_timetable`. Dates and times are in Europe/Warsaw time, values of lists are seperated by `; `
+        #[oai(path = "/export.xlsx", method = "get")]
+        async fn export_xlsx(
+            &self,
+            query: TimetableQuery,
+            coll_db: Data<&Collection<TimeTableEntry>>,
+            /// Array of columns - seperated by `;`, any of `id`, `date`, `time_beginning`, `time_ending`, `title`, `subjects`, `subject_codes`, `type_of`, `kind`, `persons`, `groups`, `building`, `room`, `students`, `status` and `details`.
+            /// Defaults to `date;time_beginning;time_ending;subjects;type_of;persons;groups;building;room`
+            columns: Query<Option<String>>,
+            /// Merge back-to-back blocks of the same class into one row
+            merge: Query<Option<bool>>,
+        ) -> XlsxResponse {
+            let (columns, entries) = match export_entries(
+                &coll_db,
+                columns.as_deref(),
+                query,
+                merge.unwrap_or(false),
             )
             .await
-            .expect("find failed!");
-            entries = cursor.try_collect().await.expect("collect failed!");
+            {
+                Ok(export) => export,
+                Err(error) => return error.into(),
+            };
+            match to_xlsx(&entries, &columns) {
+                Ok(workbook) => XlsxResponse::Xlsx(
+                    Binary(workbook),
+                    "attachment; filename=\"plan.xlsx\"".to_string(),
+                ),
+                Err(_) => {
+                    error!("{}", "XLSX error!");
+                    XlsxResponse::InternalError(Json(
+                        SigmaApiError::error(500, "XLSX Error!".to_string(), None)
+                            .expect("Error failed!"),
+                    ))
+                }
+            }
         }
+    }
+}
 
-        if entries.is_empty() {
-            error!("{}", "No entries found!");
-            SigmaApiResponse::NotFound(Json(
-                SigmaApiError::error(404, "No entries found!".to_string(), None)
-                    .expect("Error failed!"),
-            ))
-        } else {
-            entries.sort_by_key(|a| a.get_datetime_beginning());
-            SigmaApiResponse::Found(Json(SigmaApiData::new(entries)))
+struct Api;
+#[OpenApi]
+impl Api {
+    /// Get a single entry by its id
+    #[oai(path = "/get_entry", method = "get")]
+    async fn get_entry(
+        &self,
+        coll_db: Data<&Collection<TimeTableEntry>>,
+        /// Id of entry
+        id: Query<String>,
+    ) -> SigmaApiResponse<TimeTableEntry, SigmaApiError> {
+        match coll_db.find_one(doc! {"_id": id.deref()}, None).await {
+            Ok(Some(entry)) => SigmaApiResponse::Found(Json(SigmaApiData::new(entry))),
+            Ok(None) => {
+                error!("{}", "No entry found!");
+                SigmaApiResponse::NotFound(Json(
+                    SigmaApiError::error(404, "No entry found!".to_string(), None)
+                        .expect("Error failed!"),
+                ))
+            }
+            Err(_) => {
+                error!("{}", "MongoDB error!");
+                SigmaApiResponse::InternalError(Json(
+                    SigmaApiError::error(500, "MongoDB Error!".to_string(), None)
+                        .expect("Error failed!"),
+                ))
+            }
         }
     }
 
@@ -185,11 +440,7 @@ impl Api {
         &self,
         coll_db: Data<&Collection<TimeTableEntry>>,
     ) -> SigmaApiResponse<Vec<String>, SigmaApiError> {
-        if let Ok(cursor) = coll_db.distinct("groups", None, None).await {
-            let groups: Vec<String> = cursor
-                .into_iter()
-                .map(|entry| entry.to_string().replace('\"', ""))
-                .collect();
+        if let Ok(groups) = find_groups(&coll_db).await {
             if groups.is_empty() {
                 error!("{}", "No groups found!");
                 SigmaApiResponse::NotFound(Json(
@@ -207,26 +458,13 @@ impl Api {
             ))
         }
     }
-    /// Get all avaliable tutors
+    /// Get all avaliable tutors, one per person with every spelling of their name
     #[oai(path = "/get_tutors", method = "get")]
     async fn get_tutors(
         &self,
         coll_db: Data<&Collection<TimeTableEntry>>,
-    ) -> SigmaApiResponse<Vec<String>, SigmaApiError> {
-        if let Ok(cursor) = coll_db.distinct("persons", None, None).await {
-            let tutors: Vec<String> = cursor
-                .into_iter()
-                .flat_map(|entries| {
-                    if let Some(found_entries) = entries.as_array() {
-                        found_entries
-                            .iter()
-                            .map(|entry| entry.to_string().replace('\"', ""))
-                            .collect()
-                    } else {
-                        vec![entries.to_string().replace('\"', "")]
-                    }
-                })
-                .collect();
+    ) -> SigmaApiResponse<Vec<Tutor>, SigmaApiError> {
+        if let Ok(tutors) = find_tutors(&coll_db).await {
             if tutors.is_empty() {
                 error!("{}", "No tutors found!");
                 SigmaApiResponse::NotFound(Json(
@@ -245,3 +483,68 @@ impl Api {
         }
     }
 }
+
+/// Error of a query with an invalid parameter
+fn invalid_query(cause: String) -> Json<SigmaApiError> {
+    error!("{}", "Invalid query!");
+    Json(
+        SigmaApiError::error(400, "Invalid query!".to_string(), Some(cause))
+            .expect("Error failed!"),
+    )
+}
+
+/// Error of a failed database query
+fn mongo_error() -> Json<SigmaApiError> {
+    error!("{}", "MongoDB error!");
+    Json(SigmaApiError::error(500, "MongoDB Error!".to_string(), None).expect("Error failed!"))
+}
+
+/// Error of an endpoint without pages matching more than `MAX_UNPAGED_ENTRIES`
+fn too_many_entries() -> Json<SigmaApiError> {
+    error!("{}", "Too many entries!");
+    Json(
+        SigmaApiError::error(
+            400,
+            "Too many entries!".to_string(),
+            Some(format!(
+                "More than {MAX_UNPAGED_ENTRIES} entries match, narrow the filters down, e.g. with `date_from` and `date_to`"
+            )),
+        )
+        .expect("Error failed!"),
+    )
+}
+
+/// Columns and sorted entries of an export
+async fn export_entries(
+    coll_db: &Collection<TimeTableEntry>,
+    columns: Option<&str>,
+    query: TimetableQuery,
+    merge: bool,
+) -> Result<(Vec<Column>, Vec<TimeTableEntry>), ExportError> {
+    let columns = match parse_columns(columns) {
+        Ok(columns) => columns,
+        Err(column) => {
+            error!("{}", "Unknown column!");
+            return Err(ExportError::BadRequest(Json(
+                SigmaApiError::error(
+                    400,
+                    "Unknown column!".to_string(),
+                    Some(format!("No column named `{column}`")),
+                )
+                .expect("Error failed!"),
+            )));
+        }
+    };
+    let filter = query
+        .filter()
+        .map_err(|cause| ExportError::BadRequest(invalid_query(cause)))?;
+    let mut entries = find_all(coll_db, &filter)
+        .await
+        .map_err(|_| ExportError::InternalError(mongo_error()))?
+        .ok_or_else(|| ExportError::BadRequest(too_many_entries()))?;
+    if merge {
+        entries = merge_entries(entries, chrono::Duration::minutes(MERGE_MAX_GAP_MINUTES));
+    }
+    entries.sort_by_key(|a| a.get_datetime_beginning());
+    Ok((columns, entries))
+}
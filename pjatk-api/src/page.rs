@@ -0,0 +1,405 @@
+#![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
+
+use std::fmt::{self, Display};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use futures::stream::TryStreamExt;
+use mongodb::{
+    bson::{self, doc, Bson, Document},
+    error::Result as MongoResult,
+    options::FindOptions,
+    Collection, Cursor,
+};
+use poem::http::Uri;
+use timetable::{filter::TimetableFilter, merge::merge_groups, timetable::TimeTableEntry};
+
+/// Entries per page when no `limit` is given
+pub(crate) const DEFAULT_PAGE_SIZE: u32 = 500;
+
+/// Most entries loaded at once by endpoints without pages, e.g. exports
+pub(crate) const MAX_UNPAGED_ENTRIES: u32 = 10_000;
+
+/// Position right after the last entry of a page, entries are ordered by beginning and id.
+///
+/// Given out as URL-safe base64, so that clients treat it as opaque.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct PageCursor {
+    beginning: DateTime<Utc>,
+    id: String,
+}
+
+impl PageCursor {
+    fn after(entry: &TimeTableEntry) -> Self {
+        Self {
+            beginning: entry.get_datetime_beginning(),
+            id: entry.get_id().to_string(),
+        }
+    }
+
+    /// Parses a cursor given out in a `next` link, `None` if it wasn't one
+    pub(crate) fn parse(cursor: &str) -> Option<Self> {
+        let cursor = String::from_utf8(URL_SAFE_NO_PAD.decode(cursor).ok()?).ok()?;
+        let (millis, id) = cursor.split_once('.')?;
+        let beginning = Utc.timestamp_millis_opt(millis.parse().ok()?).single()?;
+        if id.is_empty() || !id.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return None;
+        }
+        Some(Self {
+            beginning,
+            id: id.to_string(),
+        })
+    }
+
+    /// Matches the entries following the cursor
+    fn to_document(&self) -> Document {
+        let beginning = Bson::DateTime(bson::DateTime::from_chrono(self.beginning));
+        doc! {"$or": [
+            {"datetime_beginning": {"$gt": beginning.clone()}},
+            {"datetime_beginning": beginning, "_id": {"$gt": &self.id}},
+        ]}
+    }
+}
+
+impl Display for PageCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cursor = format!("{}.{}", self.beginning.timestamp_millis(), self.id);
+        f.write_str(&URL_SAFE_NO_PAD.encode(cursor))
+    }
+}
+
+/// Arguments of `find_page`, so that several pages can be asked for at once with `find_pages`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct PageRequest {
+    pub(crate) filter: TimetableFilter,
+    pub(crate) limit: u32,
+    pub(crate) after: Option<PageCursor>,
+    pub(crate) merge_gap: Option<Duration>,
+}
+
+/// Up to `limit` entries following `after`, sorted by the database, and the cursor of the next page if there is one.
+///
+/// With a `merge_gap`, a page never ends inside a class `merge_entries` with that gap would merge,
+/// so it may hold more than `limit` entries when a class spans the limit.
+pub(crate) async fn find_page(
+    coll_db: &Collection<TimeTableEntry>,
+    filter: &TimetableFilter,
+    limit: u32,
+    after: Option<&PageCursor>,
+    merge_gap: Option<Duration>,
+) -> MongoResult<(Vec<TimeTableEntry>, Option<PageCursor>)> {
+    // One more than asked for tells whether there is a next page
+    let options = FindOptions::builder()
+        .sort(page_sort())
+        .limit(i64::from(limit) + 1)
+        .build();
+    let cursor: Cursor<TimeTableEntry> = coll_db.find(page_query(filter, after), options).await?;
+    let entries: Vec<TimeTableEntry> = cursor.try_collect().await?;
+    finish_page(coll_db, filter, entries, limit, merge_gap).await
+}
+
+/// Pages of every request in a single query, in the order of the requests
+pub(crate) async fn find_pages(
+    coll_db: &Collection<TimeTableEntry>,
+    requests: &[PageRequest],
+) -> MongoResult<Vec<(Vec<TimeTableEntry>, Option<PageCursor>)>> {
+    if let [request] = requests {
+        let page = find_page(
+            coll_db,
+            &request.filter,
+            request.limit,
+            request.after.as_ref(),
+            request.merge_gap,
+        )
+        .await?;
+        return Ok(vec![page]);
+    }
+    // Every page is a pipeline of its own, marking its entries with its index
+    let mut pipelines = requests.iter().enumerate().map(|(index, request)| {
+        vec![
+            doc! {"$match": page_query(&request.filter, request.after.as_ref())},
+            doc! {"$sort": page_sort()},
+            doc! {"$limit": i64::from(request.limit) + 1},
+            doc! {"$addFields": {"page": index as i64}},
+        ]
+    });
+    let mut pipeline = match pipelines.next() {
+        Some(pipeline) => pipeline,
+        None => return Ok(vec![]),
+    };
+    for other in pipelines {
+        pipeline.push(doc! {"$unionWith": {"coll": coll_db.name(), "pipeline": other}});
+    }
+    let cursor = coll_db.aggregate(pipeline, None).await?;
+    let documents: Vec<Document> = cursor.try_collect().await?;
+    let mut pages: Vec<Vec<TimeTableEntry>> = vec![vec![]; requests.len()];
+    for document in documents {
+        let index = document.get_i64("page").unwrap_or_default() as usize;
+        if let Some(page) = pages.get_mut(index) {
+            page.push(bson::from_document(document)?);
+        }
+    }
+    let mut finished = Vec::with_capacity(requests.len());
+    for (request, mut entries) in requests.iter().zip(pages) {
+        // A union keeps the order of each pipeline in practice, but doesn't promise to
+        entries.sort_by(|a, b| {
+            (a.get_datetime_beginning(), a.get_id()).cmp(&(b.get_datetime_beginning(), b.get_id()))
+        });
+        let page = finish_page(
+            coll_db,
+            &request.filter,
+            entries,
+            request.limit,
+            request.merge_gap,
+        )
+        .await?;
+        finished.push(page);
+    }
+    Ok(finished)
+}
+
+/// Cuts the sorted entries following a cursor, up to one more than `limit`, into a page and the
+/// cursor of the next.
+///
+/// With a `merge_gap`, entries beginning within the gap of the window are loaded until the page
+/// can end between merged classes.
+async fn finish_page(
+    coll_db: &Collection<TimeTableEntry>,
+    filter: &TimetableFilter,
+    mut entries: Vec<TimeTableEntry>,
+    limit: u32,
+    merge_gap: Option<Duration>,
+) -> MongoResult<(Vec<TimeTableEntry>, Option<PageCursor>)> {
+    let gap = match merge_gap {
+        Some(gap) if entries.len() > limit as usize => gap,
+        _ => return Ok(cut_page(entries, limit)),
+    };
+    let mut complete = false;
+    let end = loop {
+        if let Some(end) = merge_cut(&entries, limit as usize, gap, complete) {
+            break end;
+        }
+        let last = entries.last().map(PageCursor::after);
+        let ending = entries
+            .iter()
+            .map(TimeTableEntry::get_datetime_ending)
+            .max()
+            .expect("Windows past the limit aren't empty");
+        let horizon = bson::DateTime::from_chrono(ending + gap);
+        let query = doc! {"$and": [
+            page_query(filter, last.as_ref()),
+            {"datetime_beginning": {"$lte": horizon}},
+        ]};
+        let options = FindOptions::builder().sort(page_sort()).build();
+        let cursor: Cursor<TimeTableEntry> = coll_db.find(query, options).await?;
+        let more: Vec<TimeTableEntry> = cursor.try_collect().await?;
+        complete = more.is_empty();
+        entries.extend(more);
+    };
+    if end < entries.len() {
+        entries.truncate(end);
+        let next = entries.last().map(PageCursor::after);
+        return Ok((entries, next));
+    }
+    // The whole window fits, whatever follows begins too late to be merged with it
+    let next = entries.last().map(PageCursor::after);
+    let options = FindOptions::builder().limit(1).build();
+    let following = coll_db
+        .find(page_query(filter, next.as_ref()), options)
+        .await?
+        .try_collect::<Vec<TimeTableEntry>>()
+        .await?;
+    Ok((entries, next.filter(|_| !following.is_empty())))
+}
+
+/// Matches the entries of the filter following the cursor
+fn page_query(filter: &TimetableFilter, after: Option<&PageCursor>) -> Document {
+    let query = filter.to_document();
+    match after {
+        Some(after) => doc! {"$and": [query, after.to_document()]},
+        None => query,
+    }
+}
+
+fn page_sort() -> Document {
+    doc! {"datetime_beginning": 1, "_id": 1}
+}
+
+/// Cuts the sorted entries, up to one more than `limit`, into a page and the cursor of the next
+fn cut_page(
+    mut entries: Vec<TimeTableEntry>,
+    limit: u32,
+) -> (Vec<TimeTableEntry>, Option<PageCursor>) {
+    if entries.len() <= limit as usize {
+        return (entries, None);
+    }
+    entries.truncate(limit as usize);
+    let next = entries.last().map(PageCursor::after);
+    (entries, next)
+}
+
+/// Every entry matching the filter, `None` when there are more than `MAX_UNPAGED_ENTRIES`
+pub(crate) async fn find_all(
+    coll_db: &Collection<TimeTableEntry>,
+    filter: &TimetableFilter,
+) -> MongoResult<Option<Vec<TimeTableEntry>>> {
+    let (entries, next) = find_page(coll_db, filter, MAX_UNPAGED_ENTRIES, None, None).await?;
+    Ok(next.is_none().then_some(entries))
+}
+
+/// Length of a page of the sorted entries that doesn't split a class merged with `gap`, the
+/// longest up to `limit` or else the shortest, `None` if the window has to grow to tell.
+///
+/// Unless the window is `complete`, i.e. nothing beyond it begins within `gap` of its end, entries
+/// beyond it may still be merged into any class that ends within `gap` of its last beginning.
+fn merge_cut(
+    entries: &[TimeTableEntry],
+    limit: usize,
+    gap: Duration,
+    complete: bool,
+) -> Option<usize> {
+    let last_beginning = entries.last()?.get_datetime_beginning();
+    // Whether the page can end right before the entry at each index
+    let mut cuts = vec![true; entries.len() + 1];
+    cuts[0] = false;
+    for group in merge_groups(entries, gap) {
+        let first = *group.iter().min().expect("Groups aren't empty");
+        let last = *group.iter().max().expect("Groups aren't empty");
+        let ending = group
+            .iter()
+            .map(|index| entries[*index].get_datetime_ending())
+            .max()
+            .expect("Groups aren't empty");
+        let open = !complete && ending + gap >= last_beginning;
+        let end = if open { entries.len() } else { last };
+        cuts[first + 1..=end]
+            .iter_mut()
+            .for_each(|cut| *cut = false);
+    }
+    let valid = || (0..cuts.len()).filter(|index| cuts[*index]);
+    valid()
+        .take_while(|cut| *cut <= limit)
+        .last()
+        .or_else(|| valid().find(|cut| *cut > limit))
+}
+
+/// Link repeating the request for the page at `cursor`
+pub(crate) fn page_link(uri: &Uri, cursor: &PageCursor) -> String {
+    let cursor = format!("cursor={cursor}");
+    let mut pairs: Vec<&str> = uri
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|pair| !pair.is_empty() && pair.split('=').next() != Some("cursor"))
+        .collect();
+    pairs.push(&cursor);
+    format!("{}?{}", uri.path(), pairs.join("&"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use timetable::fixtures::{mock_entries, mock_entry_at};
+
+    fn monday(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 4, hour, minute, 0).unwrap()
+    }
+
+    fn block(room: &str, beginning: DateTime<Utc>, minutes: i64) -> TimeTableEntry {
+        TimeTableEntry::builder()
+            .title("Laboratorium")
+            .type_of("Ćwiczenia")
+            .building("B2020")
+            .room(room)
+            .datetime_beginning(beginning)
+            .datetime_ending(beginning + Duration::minutes(minutes))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn dense_window_ends_after_merged_classes() {
+        // Two labs in turns, every block beginning before the ones so far end
+        let entries = vec![
+            block("A/152", monday(8, 0), 45),
+            block("B/227", monday(8, 15), 45),
+            block("A/152", monday(8, 45), 45),
+            block("B/227", monday(9, 0), 45),
+            block("C/325", monday(9, 30), 30),
+        ];
+        let gap = Duration::minutes(15);
+
+        assert_eq!(merge_cut(&entries[..4], 3, gap, false), None);
+        // The second lab could still go on at 9:45
+        assert_eq!(merge_cut(&entries, 3, gap, false), None);
+        assert_eq!(merge_cut(&entries, 3, gap, true), Some(4));
+    }
+
+    #[test]
+    fn page_ends_between_classes_within_limit() {
+        let entries = vec![
+            block("A/152", monday(8, 0), 90),
+            block("A/152", monday(9, 30), 90),
+            block("B/227", monday(12, 0), 60),
+            block("B/227", monday(13, 0), 60),
+        ];
+
+        assert_eq!(
+            merge_cut(&entries, 3, Duration::minutes(15), false),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn class_spanning_window_needs_more_entries() {
+        let entries: Vec<TimeTableEntry> = (0..4)
+            .map(|block_index| {
+                block(
+                    "A/152",
+                    monday(8, 0) + Duration::minutes(45 * block_index),
+                    45,
+                )
+            })
+            .collect();
+        let gap = Duration::minutes(15);
+
+        assert_eq!(merge_cut(&entries, 3, gap, false), None);
+        assert_eq!(merge_cut(&entries, 3, gap, true), Some(4));
+    }
+
+    #[test]
+    fn page_without_merge_ends_at_limit() {
+        let entries = mock_entries(monday(8, 0), 4);
+        let third = PageCursor::after(&entries[2]);
+
+        let (page, next) = cut_page(entries, 3);
+        assert_eq!(page.len(), 3);
+        assert_eq!(next, Some(third));
+        assert_eq!(cut_page(mock_entries(monday(8, 0), 3), 3).1, None);
+    }
+
+    #[test]
+    fn cursor_round_trips() {
+        let cursor = PageCursor::after(&mock_entry_at(monday(8, 0)));
+        let encoded = cursor.to_string();
+
+        assert!(encoded
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_'));
+        assert_eq!(PageCursor::parse(&encoded), Some(cursor));
+    }
+
+    #[test]
+    fn cursor_rejects_anything_else() {
+        let cursor = PageCursor::after(&mock_entry_at(monday(8, 0)));
+        let plain = format!("{}.{}", monday(8, 0).timestamp_millis(), cursor.id);
+
+        assert_eq!(PageCursor::parse(&plain), None);
+        assert_eq!(PageCursor::parse("not a cursor"), None);
+        assert_eq!(
+            PageCursor::parse(&URL_SAFE_NO_PAD.encode("1709539200000.xyz")),
+            None
+        );
+        assert_eq!(PageCursor::parse(&URL_SAFE_NO_PAD.encode("soon.abc")), None);
+    }
+}
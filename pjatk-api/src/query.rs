@@ -0,0 +1,350 @@
+#![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, Utc};
+use chrono_tz::{Europe::Warsaw, Tz};
+use futures::stream::TryStreamExt;
+use mongodb::{
+    bson::{doc, Bson, Document},
+    error::Result as MongoResult,
+    Collection, Cursor,
+};
+use timetable::{
+    filter::{ListMatch, RangeMode, TimetableFilter},
+    group::{GroupCode, StudyMode},
+    kind::EntryKind,
+    person::{PersonAliases, Tutor},
+    timetable::TimeTableEntry,
+};
+
+use crate::dates::{parse_date, parse_timezone, RangeEnd, RelativeRange};
+
+/// Beginning and end of search, either unbounded when missing
+type SearchRange = (Option<DateTime<Utc>>, Option<DateTime<Utc>>);
+
+/// Filters shared by every endpoint returning entries, see `get_timetable` for their meaning
+#[derive(Default)]
+pub(crate) struct TimetableQuery {
+    pub date_from: Option<String>,
+    pub date_to: Option<String>,
+    pub when: Option<RelativeRange>,
+    pub range_mode: Option<RangeMode>,
+    pub tz: Option<String>,
+    pub groups: Option<String>,
+    pub tutors: Option<String>,
+    pub subjects: Option<String>,
+    pub subject_codes: Option<String>,
+    pub rooms: Option<String>,
+    pub buildings: Option<String>,
+    pub list_match: Option<ListMatch>,
+    pub min_students: Option<u32>,
+    pub faculty: Option<String>,
+    pub study_mode: Option<StudyMode>,
+    pub degree: Option<u8>,
+    pub semester: Option<u8>,
+    pub group_number: Option<u32>,
+    pub type_of: Option<EntryKind>,
+    pub remote: Option<bool>,
+    pub hide_cancelled: Option<bool>,
+}
+
+/// Declares endpoints of `impl` taking every filter of `TimetableQuery` as a query parameter.
+///
+/// Each endpoint names the query as its first parameter, e.g. `query: TimetableQuery`, and lists
+/// its own parameters after it. The filters are added after those and gathered into the query
+/// before the body runs, so the parameters are documented once for every endpoint.
+macro_rules! timetable_endpoints {
+    (
+        impl $api:ident {
+            $(
+                $(#[$($meta:tt)*])*
+                async fn $name:ident(
+                    &self,
+                    $query:ident: TimetableQuery
+                    $(, $(#[$($param_meta:tt)*])* $param:ident: $param_ty:ty)* $(,)?
+                ) -> $response:ty $body:block
+            )*
+        }
+    ) => {
+        #[poem_openapi::OpenApi]
+        impl $api {
+            $(
+                $(#[$($meta)*])*
+                #[allow(clippy::too_many_arguments)]
+                async fn $name(
+                    &self,
+                    $($(#[$($param_meta)*])* $param: $param_ty,)*
+                    /// Beginning of search - unix timestamp, ISO 8601 datetime or `YYYY-MM-DD` day
+                    date_from: poem_openapi::param::Query<Option<String>>,
+                    /// End of search - unix timestamp, ISO 8601 datetime or `YYYY-MM-DD` day, which is included
+                    date_to: poem_openapi::param::Query<Option<String>>,
+                    /// Search range relative to the current day in Europe/Warsaw, instead of `date_from` and `date_to`
+                    when: poem_openapi::param::Query<Option<$crate::dates::RelativeRange>>,
+                    /// Whether entries have to `overlap` the search range, the default, or be `contained` in it
+                    range_mode: poem_openapi::param::Query<Option<timetable::filter::RangeMode>>,
+                    /// IANA time zone of days and datetimes without an offset, e.g. `America/New_York`, defaults to `Europe/Warsaw`
+                    tz: poem_openapi::param::Query<Option<String>>,
+                    /// Array of groups to only search for - seperated by `;`
+                    groups: poem_openapi::param::Query<Option<String>>,
+                    /// Array of tutors to only search for - seperated by `;`, any spelling or id of a tutor
+                    tutors: poem_openapi::param::Query<Option<String>>,
+                    /// Array of subject names to only search for - seperated by `;`
+                    subjects: poem_openapi::param::Query<Option<String>>,
+                    /// Array of subject codes to only search for - seperated by `;`, e.g. `SOP`
+                    subject_codes: poem_openapi::param::Query<Option<String>>,
+                    /// Array of rooms to only search for - seperated by `;`, e.g. `B/227`
+                    rooms: poem_openapi::param::Query<Option<String>>,
+                    /// Array of buildings to only search for - seperated by `;`
+                    buildings: poem_openapi::param::Query<Option<String>>,
+                    /// Whether entries of `any` of the groups and tutors are returned or only those shared by `all` of them
+                    #[oai(name = "match")]
+                    list_match: poem_openapi::param::Query<Option<timetable::filter::ListMatch>>,
+                    /// Only entries with more than this many enrolled students
+                    min_students: poem_openapi::param::Query<Option<u32>>,
+                    /// Only groups of this faculty, e.g. `WI`
+                    faculty: poem_openapi::param::Query<Option<String>>,
+                    /// Only groups of this mode of studies
+                    study_mode: poem_openapi::param::Query<Option<timetable::group::StudyMode>>,
+                    /// Only groups of this degree level - `1` or `2`
+                    degree: poem_openapi::param::Query<Option<u8>>,
+                    /// Only groups of this semester
+                    semester: poem_openapi::param::Query<Option<u8>>,
+                    /// Only groups with this number
+                    group_number: poem_openapi::param::Query<Option<u32>>,
+                    /// Only entries of this kind, e.g. `lecture` or `reservation`
+                    #[oai(name = "type")]
+                    type_of: poem_openapi::param::Query<Option<timetable::kind::EntryKind>>,
+                    /// Only remote (`true`) or only on-site (`false`) entries
+                    remote: poem_openapi::param::Query<Option<bool>>,
+                    /// Leave out entries announced as cancelled
+                    hide_cancelled: poem_openapi::param::Query<Option<bool>>,
+                ) -> $response {
+                    let $query = $crate::query::TimetableQuery {
+                        date_from: date_from.0,
+                        date_to: date_to.0,
+                        when: when.0,
+                        range_mode: range_mode.0,
+                        tz: tz.0,
+                        groups: groups.0,
+                        tutors: tutors.0,
+                        subjects: subjects.0,
+                        subject_codes: subject_codes.0,
+                        rooms: rooms.0,
+                        buildings: buildings.0,
+                        list_match: list_match.0,
+                        min_students: min_students.0,
+                        faculty: faculty.0,
+                        study_mode: study_mode.0,
+                        degree: degree.0,
+                        semester: semester.0,
+                        group_number: group_number.0,
+                        type_of: type_of.0,
+                        remote: remote.0,
+                        hide_cancelled: hide_cancelled.0,
+                    };
+                    $body
+                }
+            )*
+        }
+    };
+}
+pub(crate) use timetable_endpoints;
+
+impl TimetableQuery {
+    /// Filter of the query, the cause of the error if a parameter is invalid
+    pub(crate) fn filter(&self) -> Result<TimetableFilter, String> {
+        let timezone = self.timezone()?.unwrap_or(Warsaw);
+        let mut filter = TimetableFilter::new()
+            .groups(split_list(&self.groups))
+            .tutors(split_list(&self.tutors))
+            .subjects(split_list(&self.subjects))
+            .subject_codes(split_list(&self.subject_codes))
+            .rooms(split_list(&self.rooms))
+            .buildings(split_list(&self.buildings))
+            .kinds(self.type_of.clone())
+            .list_match(self.list_match.unwrap_or_default())
+            .range_mode(self.range_mode.unwrap_or_default())
+            .hide_cancelled(self.hide_cancelled.unwrap_or(false));
+        let (date_from, date_to) = self.range(timezone)?;
+        if let Some(date_from) = date_from {
+            filter = filter.date_from(date_from);
+        }
+        if let Some(date_to) = date_to {
+            filter = filter.date_to(date_to);
+        }
+        if let Some(min_students) = self.min_students {
+            filter = filter.min_students(min_students);
+        }
+        if let Some(faculty) = &self.faculty {
+            filter = filter.faculty(faculty);
+        }
+        if let Some(study_mode) = self.study_mode {
+            filter = filter.study_mode(study_mode);
+        }
+        if let Some(degree) = self.degree {
+            filter = filter.degree(degree);
+        }
+        if let Some(semester) = self.semester {
+            filter = filter.semester(semester);
+        }
+        if let Some(group_number) = self.group_number {
+            filter = filter.group_number(group_number);
+        }
+        if let Some(remote) = self.remote {
+            filter = filter.remote(remote);
+        }
+        Ok(filter)
+    }
+
+    /// Beginning and end of search, given with `date_from` and `date_to` or relative to now with `when`
+    fn range(&self, timezone: Tz) -> Result<SearchRange, String> {
+        if let Some(when) = self.when {
+            if self.date_from.is_some() || self.date_to.is_some() {
+                return Err("`when` can't be combined with `date_from` or `date_to`".to_string());
+            }
+            let (date_from, date_to) = when
+                .resolve(Utc::now())
+                .ok_or_else(|| "`when` is out of range".to_string())?;
+            return Ok((Some(date_from), Some(date_to)));
+        }
+        let date_from = self
+            .date_from
+            .as_deref()
+            .map(|date_from| parse_date("date_from", date_from, timezone, RangeEnd::From))
+            .transpose()?;
+        let date_to = self
+            .date_to
+            .as_deref()
+            .map(|date_to| parse_date("date_to", date_to, timezone, RangeEnd::To))
+            .transpose()?;
+        Ok((date_from, date_to))
+    }
+
+    /// Time zone given with `tz`
+    pub(crate) fn timezone(&self) -> Result<Option<Tz>, String> {
+        self.tz.as_deref().map(parse_timezone).transpose()
+    }
+}
+
+/// Entries matching the filter, fetched with a single query
+pub(crate) async fn find_filtered(
+    coll_db: &Collection<TimeTableEntry>,
+    filter: &TimetableFilter,
+) -> MongoResult<Vec<TimeTableEntry>> {
+    let cursor: Cursor<TimeTableEntry> = coll_db.find(filter.to_document(), None).await?;
+    cursor.try_collect().await
+}
+
+/// Values of a list parameter separated by `;`
+fn split_list(list: &Option<String>) -> Vec<&str> {
+    list.as_deref()
+        .unwrap_or_default()
+        .split_terminator(';')
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+/// Every group with an entry, normalised and sorted
+pub(crate) async fn find_groups(coll_db: &Collection<TimeTableEntry>) -> MongoResult<Vec<String>> {
+    let cursor = coll_db.distinct("groups", None, None).await?;
+    Ok(cursor
+        .into_iter()
+        .map(|entry| GroupCode::normalise(&entry.to_string().replace('\"', "")))
+        .collect::<BTreeSet<String>>()
+        .into_iter()
+        .collect())
+}
+
+/// Every tutor with an entry, one per person with every spelling of their name
+pub(crate) async fn find_tutors(coll_db: &Collection<TimeTableEntry>) -> MongoResult<Vec<Tutor>> {
+    tutors_of(coll_db, doc! {}).await
+}
+
+/// Tutor by id, out of their own entries only
+pub(crate) async fn find_tutor(
+    coll_db: &Collection<TimeTableEntry>,
+    id: &str,
+) -> MongoResult<Option<Tutor>> {
+    Ok(tutors_of(coll_db, doc! {"tutors.id": id})
+        .await?
+        .into_iter()
+        .find(|tutor| tutor.person().id() == id))
+}
+
+/// Tutors of the entries matching the query
+async fn tutors_of(
+    coll_db: &Collection<TimeTableEntry>,
+    query: Document,
+) -> MongoResult<Vec<Tutor>> {
+    let pipeline = [
+        doc! {"$match": query},
+        doc! {"$unwind": "$persons"},
+        doc! {"$group": {"_id": "$persons", "count": {"$sum": 1}}},
+    ];
+    let cursor = coll_db.aggregate(pipeline, None).await?;
+    let counts: Vec<Document> = cursor.try_collect().await?;
+    let mut aliases = PersonAliases::new();
+    for count in counts {
+        if let Ok(person) = count.get_str("_id") {
+            aliases.insert(person, count.get_i32("count").unwrap_or(1) as usize);
+        }
+    }
+    Ok(aliases.tutors())
+}
+
+/// Every room with an entry as building and room, sorted
+pub(crate) async fn find_rooms(
+    coll_db: &Collection<TimeTableEntry>,
+) -> MongoResult<Vec<(String, String)>> {
+    let pipeline = [
+        doc! {"$group": {"_id": {"building": "$building", "room": "$room"}}},
+        doc! {"$sort": {"_id.building": 1, "_id.room": 1}},
+    ];
+    let cursor = coll_db.aggregate(pipeline, None).await?;
+    let rooms: Vec<Document> = cursor.try_collect().await?;
+    Ok(rooms
+        .iter()
+        .filter_map(|room| room.get_document("_id").ok())
+        .filter_map(|room| {
+            Some((
+                room.get_str("building").ok()?.to_string(),
+                room.get_str("room").ok()?.to_string(),
+            ))
+        })
+        .collect())
+}
+
+/// Every subject with an entry along with its code when known, sorted by name
+pub(crate) async fn find_subjects(
+    coll_db: &Collection<TimeTableEntry>,
+) -> MongoResult<Vec<(String, Option<String>)>> {
+    let pipeline = [
+        doc! {"$project": {"pairs": {"$zip": {
+            "inputs": ["$subjects", "$subject_codes"],
+            "useLongestLength": true,
+        }}}},
+        doc! {"$unwind": "$pairs"},
+        doc! {"$group": {"_id": "$pairs"}},
+    ];
+    let cursor = coll_db.aggregate(pipeline, None).await?;
+    let pairs: Vec<Document> = cursor.try_collect().await?;
+    let mut subjects: BTreeMap<String, Option<String>> = BTreeMap::new();
+    for pair in pairs {
+        let pair = match pair.get_array("_id") {
+            Ok(pair) => pair,
+            Err(_) => continue,
+        };
+        if let Some(Bson::String(name)) = pair.first() {
+            let code = match pair.get(1) {
+                Some(Bson::String(code)) => Some(code.clone()),
+                _ => None,
+            };
+            let known = subjects.entry(name.clone()).or_default();
+            if known.is_none() {
+                *known = code;
+            }
+        }
+    }
+    Ok(subjects.into_iter().collect())
+}
@@ -2,8 +2,9 @@
 use api_utils::SigmaApiData;
 use api_utils::SigmaApiError;
 use api_utils::SigmaApiResponse;
+use mongodb::{bson::Document, Collection};
 use poem_openapi::payload::Json;
-use thirtyfour::WebDriver;
+use timetable::migration::{migrate_collection, MigrationReport};
 
 use tokio::sync::mpsc::UnboundedSender;
 
@@ -11,8 +12,9 @@ use poem::web::Data;
 use poem_openapi::{param::Path, OpenApi};
 
 use std::sync::Arc;
+use tracing::{error, info};
 
-use crate::scraper::{parse_timetable_day, EntryToSend};
+use crate::scraper::{EntryToSend, Scraper};
 
 pub(crate) struct Api;
 #[OpenApi]
@@ -20,38 +22,77 @@ impl Api {
     #[oai(path = "/fetch_days/:beginning_date/:amount_of_days", method = "get")]
     async fn fetch_days(
         &self,
-        web_driver: Data<&Arc<WebDriver>>,
+        scraper: Data<&Arc<Scraper>>,
         tx: Data<&UnboundedSender<EntryToSend>>,
         beginning_date: Path<String>,
         amount_of_days: Path<Option<u8>>,
     ) -> SigmaApiResponse<String, SigmaApiError> {
-        let date = chrono::NaiveDate::parse_from_str(&beginning_date.0, "%Y-%m-%d");
-        if date.is_err() {
-            return SigmaApiResponse::BadRequest(Json(
-                SigmaApiError::error(400, "Parsing error".to_string(), None)
-                    .expect("Error failed!"),
-            ));
-        } else {
-            let checked_beginning = date.unwrap();
-            if let Some(amount_of_days) = amount_of_days.0 {
-                for date in checked_beginning.iter_days().take(amount_of_days.into()) {
-                    let date_string = date.format("%Y-%m-%d").to_string();
-                    web_driver.refresh().await.expect("refresh failed!");
-                    parse_timetable_day(&web_driver, date_string, tx.clone())
-                        .await
-                        .map_err(|err| eprintln!("{}", &err))
-                        .expect("failed!");
+        let checked_beginning =
+            match chrono::NaiveDate::parse_from_str(&beginning_date.0, "%Y-%m-%d") {
+                Ok(date) => date,
+                Err(_) => {
+                    return SigmaApiResponse::BadRequest(Json(
+                        SigmaApiError::error(400, "Parsing error".to_string(), None)
+                            .expect("Error failed!"),
+                    ));
                 }
-            } else {
-                let date_string = checked_beginning.format("%Y-%m-%d").to_string();
-                parse_timetable_day(&web_driver, date_string, tx.clone())
-                    .await
-                    .map_err(|err| eprintln!("{}", &err))
-                    .expect("failed!");
+            };
+        let dates: Vec<_> = if let Some(amount_of_days) = amount_of_days.0 {
+            checked_beginning
+                .iter_days()
+                .take(amount_of_days.into())
+                .collect()
+        } else {
+            vec![checked_beginning]
+        };
+        // The browser is closed once scraping stops, whether every day was scraped or not
+        let mut failure = None;
+        for date in dates {
+            let date_string = date.format("%Y-%m-%d").to_string();
+            if amount_of_days.0.is_some() {
+                scraper.refresh().await.expect("refresh failed!");
+            }
+            if let Err(err) = scraper.parse_timetable_day(date_string, tx.clone()).await {
+                error!("Scraping {} failed: {}", date, err);
+                failure = Some(err.to_string());
+                break;
             }
         }
         tx.send(EntryToSend::Quit)
             .expect("Error closing browser! Restart GeckoDriver Docker container!");
-        SigmaApiResponse::Found(Json(SigmaApiData::new("Done!".to_string())))
+        match failure {
+            None => SigmaApiResponse::Found(Json(SigmaApiData::new("Done!".to_string()))),
+            Some(cause) => SigmaApiResponse::InternalError(Json(
+                SigmaApiError::error(500, "Scraping error".to_string(), Some(cause))
+                    .expect("Error failed!"),
+            )),
+        }
+    }
+    /// Upgrade stored entries to the current schema version
+    #[oai(path = "/migrate", method = "post")]
+    async fn migrate(
+        &self,
+        collection: Data<&Collection<Document>>,
+    ) -> SigmaApiResponse<MigrationReport, SigmaApiError> {
+        match migrate_collection(&collection).await {
+            Ok(report) => {
+                info!(
+                    "Migrated {} documents, {} under a new id",
+                    report.migrated + report.rekeyed,
+                    report.rekeyed
+                );
+                for failure in &report.failed {
+                    error!("{}", failure);
+                }
+                SigmaApiResponse::Found(Json(SigmaApiData::new(report)))
+            }
+            Err(err) => {
+                error!("Migration failed: {}", err);
+                SigmaApiResponse::InternalError(Json(
+                    SigmaApiError::error(500, "MongoDB Error!".to_string(), Some(err.to_string()))
+                        .expect("Error failed!"),
+                ))
+            }
+        }
     }
 }
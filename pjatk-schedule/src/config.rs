@@ -4,6 +4,8 @@ use std::{error::Error, sync::Arc};
 use mongodb::{options::ClientOptions, Client};
 use thirtyfour::{DesiredCapabilities, PageLoadStrategy, WebDriver};
 
+use crate::{http_scraper::HttpScraper, scraper::Scraper};
+
 pub(crate) static ENVIROMENT: Env = Env::new();
 
 #[allow(non_snake_case)]
@@ -17,6 +19,8 @@ pub(crate) struct Env {
     pub MONGO_INITDB_DATABASE: &'static str,
     pub MONGO_INITDB_COLLECTION: &'static str,
     pub AUTH_KEY: &'static str,
+    pub PJATK_SCRAPPER_BACKEND: &'static str,
+    pub PJATK_PLAN_URL: &'static str,
 }
 
 impl Env {
@@ -31,13 +35,17 @@ impl Env {
             MONGO_INITDB_DATABASE: "MONGO_INITDB_DATABASE",
             MONGO_INITDB_COLLECTION: "MONGO_INITDB_COLLECTION",
             AUTH_KEY: "AUTH_KEY",
+            PJATK_SCRAPPER_BACKEND: "PJATK_SCRAPPER_BACKEND",
+            PJATK_PLAN_URL: "PJATK_PLAN_URL",
         }
     }
 }
 
+const DEFAULT_PLAN_URL: &str = "https://planzajec.pjwstk.edu.pl/PlanOgolny3.aspx";
+
 pub(crate) struct Config {
     client_db: Client,
-    client_webdriver: Arc<WebDriver>,
+    client_scraper: Arc<Scraper>,
     port: u16,
     server_url_with_protocol: String,
 }
@@ -46,7 +54,7 @@ impl Config {
     pub(crate) async fn new() -> Result<Self, Box<dyn Error>> {
         Ok(Self {
             client_db: Config::connect_db().await?,
-            client_webdriver: Arc::new(Config::init_pjatk_client().await?),
+            client_scraper: Arc::new(Config::init_scraper().await?),
             port: std::env::var(ENVIROMENT.PJATK_SCRAPPER_PORT)?.parse()?,
             server_url_with_protocol: std::env::var(ENVIROMENT.PJATK_SCRAPPER_URL_WITH_PROTOCOL)?,
         })
@@ -54,8 +62,8 @@ impl Config {
     pub fn get_db(&self) -> &Client {
         &self.client_db
     }
-    pub fn get_webdriver(&self) -> &Arc<WebDriver> {
-        &self.client_webdriver
+    pub fn get_scraper(&self) -> &Arc<Scraper> {
+        &self.client_scraper
     }
     pub fn get_complete_server_url(&self) -> String {
         format!("{0}:{1}/api", self.server_url_with_protocol, self.port)
@@ -76,14 +84,28 @@ impl Config {
         let client_db = Client::with_options(client_options).expect("Client failed!");
         Ok(client_db)
     }
-    async fn init_pjatk_client() -> Result<WebDriver, Box<dyn Error>> {
+    async fn init_scraper() -> Result<Scraper, Box<dyn Error>> {
+        let plan_url = std::env::var(ENVIROMENT.PJATK_PLAN_URL)
+            .ok()
+            .filter(|url| !url.is_empty())
+            .unwrap_or_else(|| DEFAULT_PLAN_URL.to_string());
+        match std::env::var(ENVIROMENT.PJATK_SCRAPPER_BACKEND)
+            .unwrap_or_default()
+            .as_str()
+        {
+            "" | "webdriver" => Ok(Scraper::WebDriver(
+                Config::init_pjatk_client(&plan_url).await?,
+            )),
+            "http" => Ok(Scraper::Http(HttpScraper::new(plan_url)?)),
+            other => Err(format!("Unknown scraper backend: {other}").into()),
+        }
+    }
+    async fn init_pjatk_client(plan_url: &str) -> Result<WebDriver, Box<dyn Error>> {
         let mut caps = DesiredCapabilities::firefox();
         caps.set_headless()?;
         caps.set_page_load_strategy(PageLoadStrategy::None)?;
         let client = WebDriver::new("http://geckodriver:4444", caps).await?;
-        client
-            .goto("https://planzajec.pjwstk.edu.pl/PlanOgolny3.aspx")
-            .await?;
+        client.goto(plan_url).await?;
         Ok(client)
     }
 }
@@ -0,0 +1,269 @@
+#![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
+use std::{collections::BTreeMap, error::Error};
+
+use kuchiki::traits::TendrilSink;
+use reqwest::Client;
+use serde_json::json;
+use timetable::{
+    altapi_timetable::{parse_delta, AltApiDelta, TOOLTIP_PANEL_ID},
+    day_page::parse_cell_ids,
+    source::TimetableSource,
+};
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{error, info};
+
+use crate::scraper::EntryToSend;
+
+const DATE_PICKER_ID: &str = "DataPicker";
+const SCRIPT_MANAGER_ID: &str = "RadScriptManager1";
+const TOOLTIP_MANAGER_ID: &str = "RadToolTipManager1";
+
+/// Hidden fields of the ASP.NET form (`__VIEWSTATE`, `__EVENTVALIDATION`, ...), sent back with every postback
+type FormState = BTreeMap<String, String>;
+
+/// Scrapes PlanOgolny3 without a browser, by replaying the postbacks the page sends itself
+pub(crate) struct HttpScraper {
+    client: Client,
+    plan_url: String,
+}
+
+impl HttpScraper {
+    pub(crate) fn new(plan_url: String) -> Result<Self, Box<dyn Error>> {
+        let client = Client::builder().cookie_store(true).build()?;
+        Ok(Self { client, plan_url })
+    }
+
+    pub(crate) async fn parse_timetable_day(
+        &self,
+        date: String,
+        tx: UnboundedSender<EntryToSend>,
+    ) -> Result<(), Box<dyn Error>> {
+        let page = self
+            .client
+            .get(&self.plan_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        let mut form = parse_form_state(&page);
+        form.extend(date_picker_fields(&date));
+        form.insert("__EVENTTARGET".to_string(), DATE_PICKER_ID.to_string());
+        form.insert("__EVENTARGUMENT".to_string(), String::new());
+        let day_page = self
+            .client
+            .post(&self.plan_url)
+            .form(&form)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        let mut form_state = parse_form_state(&day_page);
+        let cell_ids = parse_cell_ids(&day_page);
+        info!("Found {} timetable entries", cell_ids.len());
+        for (index, cell_id) in cell_ids.iter().enumerate() {
+            let mut form = form_state.clone();
+            form.extend(date_picker_fields(&date));
+            form.extend(tooltip_fields(cell_id));
+            let delta = self
+                .client
+                .post(&self.plan_url)
+                .header("X-MicrosoftAjax", "Delta=true")
+                .header("X-Requested-With", "XMLHttpRequest")
+                .form(&form)
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await?;
+            update_form_state(&mut form_state, &delta);
+            match AltApiDelta.parse_entry(&delta) {
+                Ok(entry) => {
+                    tx.send(EntryToSend::Entry(Box::new(entry.with_source_id(cell_id))))?
+                }
+                Err(err) => error!("Skipping cell {} on {}: {}", cell_id, date, err),
+            }
+            info!("{}", index);
+        }
+        Ok(())
+    }
+}
+
+/// Reads every hidden input of the page
+fn parse_form_state(page_html: &str) -> FormState {
+    let dom = kuchiki::parse_html().one(page_html);
+    let mut form_state = FormState::new();
+    if let Ok(inputs) = dom.select("input[type=hidden]") {
+        for input in inputs {
+            let attributes = input.attributes.borrow();
+            if let Some(name) = attributes.get("name") {
+                form_state.insert(
+                    name.to_string(),
+                    attributes.get("value").unwrap_or_default().to_string(),
+                );
+            }
+        }
+    }
+    form_state
+}
+
+/// Takes the new values of hidden fields sent back in a delta response, e.g. the next `__VIEWSTATE`
+fn update_form_state(form_state: &mut FormState, delta: &str) {
+    if let Ok(records) = parse_delta(delta) {
+        for record in records.iter().filter(|record| record.kind == "hiddenField") {
+            form_state.insert(record.id.to_string(), record.content.to_string());
+        }
+    }
+}
+
+/// Fields the date picker posts when a `YYYY-MM-DD` date is typed in and confirmed
+fn date_picker_fields(date: &str) -> [(String, String); 3] {
+    let client_state = json!({
+        "enabled": true,
+        "emptyMessage": "",
+        "validationText": format!("{date}-00-00-00"),
+        "valueAsString": format!("{date}-00-00-00"),
+        "lastSetTextBoxValue": date,
+    });
+    [
+        (DATE_PICKER_ID.to_string(), date.to_string()),
+        (format!("{DATE_PICKER_ID}$dateInput"), date.to_string()),
+        (
+            format!("{DATE_PICKER_ID}_dateInput_ClientState"),
+            client_state.to_string(),
+        ),
+    ]
+}
+
+/// Fields of the async postback the tooltip manager sends when a cell is hovered
+fn tooltip_fields(cell_id: &str) -> [(String, String); 5] {
+    let client_state = json!({
+        "AjaxTargetControl": cell_id,
+        "Value": cell_id,
+    });
+    [
+        (
+            SCRIPT_MANAGER_ID.to_string(),
+            format!("{TOOLTIP_PANEL_ID}|{TOOLTIP_PANEL_ID}"),
+        ),
+        ("__EVENTTARGET".to_string(), TOOLTIP_PANEL_ID.to_string()),
+        ("__EVENTARGUMENT".to_string(), String::new()),
+        (
+            format!("{TOOLTIP_MANAGER_ID}_ClientState"),
+            client_state.to_string(),
+        ),
+        ("__ASYNCPOST".to_string(), "true".to_string()),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use timetable::fixtures;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+        sync::mpsc::unbounded_channel,
+    };
+
+    use super::*;
+
+    /// Delta of a callback without a tooltip panel
+    const EMPTY_DELTA: &str = "1|#||4|";
+
+    /// Answers like PlanOgolny3 with the recorded day, keeping every request it got
+    async fn serve_recorded_day(listener: TcpListener, requests: Arc<Mutex<Vec<String>>>) {
+        let deltas = fixtures::day_page_deltas();
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let request = read_request(&mut stream).await;
+            let body = if request
+                .to_lowercase()
+                .contains("x-microsoftajax: delta=true")
+            {
+                deltas
+                    .iter()
+                    .find(|(cell_id, _)| request.contains(&cell_id.replace(';', "%3B")))
+                    .map_or(EMPTY_DELTA, |(_, delta)| delta.as_str())
+            } else {
+                fixtures::DAY_PAGE
+            };
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+            requests.lock().unwrap().push(request);
+        }
+    }
+
+    /// Reads the head and the whole body of a single request
+    async fn read_request(stream: &mut TcpStream) -> String {
+        let mut request = vec![];
+        let mut buffer = [0; 4096];
+        loop {
+            let read = stream.read(&mut buffer).await.unwrap();
+            request.extend_from_slice(&buffer[..read]);
+            let text = String::from_utf8_lossy(&request).into_owned();
+            let complete = text.find("\r\n\r\n").is_some_and(|head_end| {
+                let content_length = text[..head_end]
+                    .lines()
+                    .find_map(|line| {
+                        let (name, value) = line.split_once(':')?;
+                        name.eq_ignore_ascii_case("content-length")
+                            .then(|| value.trim().parse().ok())?
+                    })
+                    .unwrap_or(0);
+                request.len() >= head_end + 4 + content_length
+            });
+            if complete || read == 0 {
+                return text;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn scrapes_recorded_day() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let plan_url = format!("http://{}/PlanOgolny3.aspx", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(vec![]));
+        tokio::spawn(serve_recorded_day(listener, requests.clone()));
+
+        let (tx, mut rx) = unbounded_channel();
+        HttpScraper::new(plan_url)
+            .unwrap()
+            .parse_timetable_day("2024-10-16".to_string(), tx)
+            .await
+            .unwrap();
+        let mut entries = vec![];
+        while let Some(EntryToSend::Entry(entry)) = rx.recv().await {
+            entries.push(*entry);
+        }
+
+        let deltas = fixtures::day_page_deltas();
+        let expected: Vec<_> = ["2413051;z", "2413087;z", "871204;r"]
+            .into_iter()
+            .map(|cell_id| {
+                AltApiDelta
+                    .parse_entry(&deltas[cell_id])
+                    .unwrap()
+                    .with_source_id(cell_id)
+            })
+            .collect();
+        assert_eq!(entries, expected);
+
+        let requests = requests.lock().unwrap();
+        // Page, date picker and a callback for each of the four cells
+        assert_eq!(requests.len(), 6);
+        assert!(requests[0].starts_with("GET /PlanOgolny3.aspx"));
+        assert!(requests[1].contains("DataPicker%24dateInput=2024-10-16"));
+        assert!(requests[1].contains("__VIEWSTATEGENERATOR=6A3E2C1B"));
+        assert!(requests[2..]
+            .iter()
+            .all(|request| request.contains("__ASYNCPOST=true")));
+    }
+}
@@ -7,7 +7,11 @@ use api_utils::SigmaApiError;
 use auth::BearerAuth;
 use config::Config;
 use config::ENVIROMENT;
-use mongodb::Collection;
+use mongodb::{
+    bson::{doc, Document},
+    options::ReplaceOptions,
+    Collection,
+};
 use poem::{
     listener::TcpListener, middleware::TowerLayerCompatExt, EndpointExt, Result, Route, Server,
 };
@@ -24,6 +28,7 @@ mod api;
 
 mod auth;
 mod config;
+mod http_scraper;
 mod scraper;
 
 #[tokio::main(flavor = "multi_thread")]
@@ -44,14 +49,19 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let open_api_specs = api_service.spec_endpoint();
 
     let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<EntryToSend>();
-    let client = config.get_webdriver().clone();
+    let scraper = config.get_scraper().clone();
+    let collection: Collection<Document> = config
+        .get_db()
+        .database(&std::env::var(ENVIROMENT.MONGO_INITDB_DATABASE)?)
+        .collection(&std::env::var(ENVIROMENT.MONGO_INITDB_COLLECTION)?);
 
     let app = Route::new()
         .nest("/", docs)
         .nest("/api", api_service)
         .nest("/openapi.json", open_api_specs)
-        .data(client.clone())
+        .data(scraper.clone())
         .data(tx.clone())
+        .data(collection)
         .with(tower::limit::ConcurrencyLimitLayer::new(1).compat())
         .with(tower::buffer::BufferLayer::new(100).compat())
         .with(poem::middleware::Tracing)
@@ -74,13 +84,17 @@ async fn main() -> Result<(), Box<dyn Error>> {
                         );
 
                         timetable
-                            .insert_one(entry, None)
+                            .replace_one(
+                                doc! {"_id": entry.get_id()},
+                                entry,
+                                ReplaceOptions::builder().upsert(true).build(),
+                            )
                             .await
-                            .expect("Insert failed!");
+                            .expect("Upsert failed!");
                     }
                     EntryToSend::Quit => {
-                        client
-                            .close_window()
+                        scraper
+                            .close()
                             .await
                             .expect("Error closing browser! Restart GeckoDriver Docker container!");
                         break;
@@ -1,14 +1,15 @@
 #![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
 use std::{error::Error, time::Duration};
 
-use kuchiki::traits::TendrilSink;
 use thirtyfour::{
     prelude::{ElementQueryable, ElementWaitable},
     By, Key, WebDriver,
 };
-use timetable::timetable::TimeTableEntry;
+use timetable::{day_page::parse_tooltip, timetable::TimeTableEntry};
 use tokio::sync::mpsc::UnboundedSender;
-use tracing::info;
+use tracing::{error, info};
+
+use crate::http_scraper::HttpScraper;
 
 #[derive(Debug)]
 pub(crate) enum EntryToSend {
@@ -16,7 +17,40 @@ pub(crate) enum EntryToSend {
     Quit,
 }
 
-pub(crate) async fn parse_timetable_day(
+/// Backend reading the timetable, selected with `PJATK_SCRAPPER_BACKEND`
+pub(crate) enum Scraper {
+    /// Firefox driven through geckodriver, clicking every cell
+    WebDriver(WebDriver),
+    /// Plain HTTP client replaying the postbacks of the page
+    Http(HttpScraper),
+}
+
+impl Scraper {
+    pub(crate) async fn refresh(&self) -> Result<(), Box<dyn Error>> {
+        if let Scraper::WebDriver(web_driver) = self {
+            web_driver.refresh().await?;
+        }
+        Ok(())
+    }
+    pub(crate) async fn parse_timetable_day(
+        &self,
+        date: String,
+        tx: UnboundedSender<EntryToSend>,
+    ) -> Result<(), Box<dyn Error>> {
+        match self {
+            Scraper::WebDriver(web_driver) => parse_timetable_day(web_driver, date, tx).await,
+            Scraper::Http(http_scraper) => http_scraper.parse_timetable_day(date, tx).await,
+        }
+    }
+    pub(crate) async fn close(&self) -> Result<(), Box<dyn Error>> {
+        if let Scraper::WebDriver(web_driver) = self {
+            web_driver.close_window().await?;
+        }
+        Ok(())
+    }
+}
+
+async fn parse_timetable_day(
     web_driver: &WebDriver,
     date: String,
     tx: UnboundedSender<EntryToSend>,
@@ -58,9 +92,11 @@ pub(crate) async fn parse_timetable_day(
             .first()
             .await?;
         let html = tooltip_element.inner_html().await?;
-        let tooltip_node = kuchiki::parse_html().from_utf8().one(html.as_bytes());
-        let entry: TimeTableEntry = tooltip_node.try_into()?;
-        tx.send(EntryToSend::Entry(Box::new(entry)))?;
+        let cell_id = element.id().await?.unwrap_or_default();
+        match parse_tooltip(&html) {
+            Ok(entry) => tx.send(EntryToSend::Entry(Box::new(entry.with_source_id(&cell_id))))?,
+            Err(err) => error!("Skipping cell {} on {}: {}", cell_id, date, err),
+        }
         info!("{}", index);
     }
     Ok(())
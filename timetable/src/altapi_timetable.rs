@@ -1,200 +1,117 @@
 #![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
 
-use std::{error::Error, fmt::Display};
+use crate::{
+    error::ParseError,
+    source::{TimetableSource, TooltipHtml},
+    timetable::TimeTableEntry,
+};
 
-use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
-use chrono_tz::Europe::Warsaw;
-use kuchiki::NodeRef;
-use poem_openapi::Object;
-use serde::{Deserialize, Serialize};
+/// Id of the update panel holding the tooltip
+pub const TOOLTIP_PANEL_ID: &str = "RadToolTipManager1RTMPanel";
 
-#[derive(Debug, Serialize, Deserialize, Clone, Object)]
-#[oai]
-pub struct TimeTableEntry {
-    /// Title of entry
-    pub(crate) title: Option<String>,
-    /// Persons
-    pub(crate) persons: Vec<String>,
-    /// Details of entry
-    pub(crate) details: Option<String>,
-    /// Type of entry
-    pub(crate) type_of: String,
-    /// Subjects
-    pub(crate) subjects: Vec<String>,
-    /// Subjects codes
-    pub(crate) subject_codes: Vec<String>,
-    /// Groups
-    pub(crate) groups: Option<Vec<String>>,
-    /// Count of students
-    pub(crate) students_count: Option<String>,
-    /// Building
-    pub(crate) building: String,
-    /// Room
-    pub(crate) room: String,
-    /// Date and time of beginning
-    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
-    pub(crate) datetime_beginning: DateTime<Utc>,
-    /// Date and time of ending
-    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
-    pub(crate) datetime_ending: DateTime<Utc>,
-}
-
-impl TryFrom<NodeRef> for TimeTableEntry {
-    type Error = Box<dyn Error>;
+/// ASP.NET AJAX partial postback response of the tooltip callback, as returned over plain HTTP.
+///
+/// The response is a sequence of `length|type|id|content|` records, the tooltip is
+/// the content of the `updatePanel` record of `RadToolTipManager1RTMPanel`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AltApiDelta;
 
-    fn try_from(dom: NodeRef) -> Result<Self, Self::Error> {
-        let date = get_data(&dom, "#ctl06_DataZajecLabel", "date")?;
-        let (datetime_beginning, datetime_ending) = extract_date_from_string(&dom, date)?;
-        let result = TimeTableEntry {
-            title: get_data_option(&dom, "#ctl06_TytulRezerwacjiLabel"),
-            persons: get_multiple_data(
-                &dom,
-                "#ctl06_OsobaRezerwujacaLabel, #ctl06_DydaktycyLabel",
-                "persons",
-            )?,
-            details: get_data_option(&dom, "#ctl06_OpisLabel"),
-            type_of: get_data(
-                &dom,
-                "#ctl06_TypRezerwacjiLabel, #ctl06_TypZajecLabel",
-                "type_of",
-            )?,
-            subjects: get_multiple_data(
-                &dom,
-                "#ctl06_NazwyPrzedmiotowLabel, #ctl06_NazwaPrzedmiotyLabel",
-                "subjects",
-            )?,
-            subject_codes: get_multiple_data(
-                &dom,
-                "#ctl06_KodyPrzedmiotowLabel, #ctl06_KodPrzedmiotuLabel",
-                "subject_codes",
-            )?,
-            groups: {
-                let groups = get_multiple_data(
-                    &dom,
-                    "#ctl06_GrupyStudenckieLabel, #ctl06_GrupyLabel",
-                    "groups",
-                )?;
-                if groups.iter().all(|group| group == "---") {
-                    None
-                } else {
-                    Some(groups)
-                }
-            },
-            students_count: get_data_option(&dom, "#ctl06_LiczbaStudentowLabel"),
-            building: get_data(&dom, "#ctl06_BudynekLabel", "building")?,
-            room: get_data(&dom, "#ctl06_SalaLabel", "room")?,
-            datetime_beginning,
-            datetime_ending,
-        };
-        Ok(result)
+impl TimetableSource for AltApiDelta {
+    fn parse_entry(&self, input: &str) -> Result<TimeTableEntry, ParseError> {
+        let records = parse_delta(input)?;
+        let panel = records
+            .iter()
+            .find(|record| record.kind == "updatePanel" && record.id.ends_with(TOOLTIP_PANEL_ID))
+            .ok_or(ParseError::MissingSelector {
+                selector: TOOLTIP_PANEL_ID,
+                field: "tooltip",
+            })?;
+        TooltipHtml.parse_entry(panel.content)
     }
 }
 
-fn extract_date_from_string(
-    dom: &NodeRef,
-    date: String,
-) -> Result<(DateTime<Utc>, DateTime<Utc>), Box<dyn Error>> {
-    let hour_beginning = get_data(dom, "#ctl06_GodzRozpLabel", "hour_beginning")?;
-    let hour_ending = get_data(dom, "#ctl06_GodzZakonLabel", "hour_ending")?;
-    let beginning_str = format!("{} {}", date, hour_beginning);
-    let ending_str = format!("{} {}", date, hour_ending);
-    let beginning_naive = NaiveDateTime::parse_from_str(&beginning_str, "%d.%m.%Y %T")?;
-    let ending_naive = NaiveDateTime::parse_from_str(&ending_str, "%d.%m.%Y %T")?;
-    let datetime_beginning = Warsaw
-        .from_local_datetime(&beginning_naive)
-        .single()
-        .expect("Beginning time parsing failed!")
-        .with_timezone(&Utc);
-    let datetime_ending = Warsaw
-        .from_local_datetime(&ending_naive)
-        .single()
-        .expect("Ending time parsing failed!")
-        .with_timezone(&Utc);
-    Ok((datetime_beginning, datetime_ending))
+/// Single `length|type|id|content|` record of a delta response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeltaRecord<'a> {
+    pub kind: &'a str,
+    pub id: &'a str,
+    pub content: &'a str,
 }
 
-impl TimeTableEntry {
-    pub fn get_datetime_beginning(&self) -> DateTime<Utc> {
-        self.datetime_beginning
-    }
-}
-fn get_mock_entry() -> TimeTableEntry {
-    // Sample entry
-    TimeTableEntry {
-        title: Some("Ostatni wykład".to_string()),
-        persons: vec![
-            "Niezgoda Adam".to_string(),
-            "Tomaszewski Michał".to_string(),
-        ],
-        details: Some("Podsumowanie semestru".to_string()),
-        type_of: "Wykład".to_string(),
-        subjects: vec![
-            "Systemy operacyjne".to_string(),
-            "Programowanie obiektowe i GUI".to_string(),
-        ],
-        subject_codes: vec!["SOP".to_string(), "GUI".to_string()],
-        groups: Some(vec![
-            "WIs I.2 - 46c".to_string(),
-            "WIS I.2 - 23c".to_string(),
-        ]),
-        students_count: Some("115 115 ITN".to_string()),
-        building: "B2020".to_string(),
-        room: "B/227".to_string(),
-        datetime_beginning: Utc::now(),
-        datetime_ending: Utc::now() + Duration::hours(2),
+/// Splits a delta response into its records
+pub fn parse_delta(input: &str) -> Result<Vec<DeltaRecord<'_>>, ParseError> {
+    let malformed = || ParseError::MalformedResponse {
+        selector: TOOLTIP_PANEL_ID,
+        raw: input.chars().take(200).collect(),
+    };
+    let mut records = vec![];
+    let mut rest = input;
+    while !rest.is_empty() {
+        let (length, after_length) = rest.split_once('|').ok_or_else(malformed)?;
+        let length: usize = length.parse().map_err(|_| malformed())?;
+        let (kind, after_kind) = after_length.split_once('|').ok_or_else(malformed)?;
+        let (id, after_id) = after_kind.split_once('|').ok_or_else(malformed)?;
+        let content_end = utf16_offset(after_id, length).ok_or_else(malformed)?;
+        let (content, after_content) = after_id.split_at(content_end);
+        rest = after_content.strip_prefix('|').ok_or_else(malformed)?;
+        records.push(DeltaRecord { kind, id, content });
     }
+    Ok(records)
 }
-fn get_data_option(dom: &NodeRef, selector: &'static str) -> Option<String> {
-    if let Ok(dom) = dom.select_first(selector) {
-        Some(dom.text_contents().trim().to_string())
-    } else {
-        None
+
+/// Byte offset after `units` UTF-16 code units, as delta lengths are counted the JavaScript way
+fn utf16_offset(text: &str, units: usize) -> Option<usize> {
+    let mut counted = 0;
+    for (index, c) in text.char_indices() {
+        if counted == units {
+            return Some(index);
+        }
+        counted += c.len_utf16();
     }
+    (counted == units).then_some(text.len())
 }
 
-fn get_data(
-    dom: &NodeRef,
-    selector: &'static str,
-    cause: &'static str,
-) -> Result<String, ParseError> {
-    let date = dom
-        .select_first(selector)
-        .map_err(|_| ParseError { cause })?
-        .text_contents()
-        .trim()
-        .to_string();
-    Ok(date)
-}
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
 
-fn get_multiple_data(
-    dom: &NodeRef,
-    selectors: &'static str,
-    cause: &'static str,
-) -> Result<Vec<String>, ParseError> {
-    Ok(dom
-        .select_first(selectors)
-        .map_err(|_| ParseError { cause })?
-        .text_contents()
-        .trim()
-        .split_terminator(',')
-        .into_iter()
-        .map(|a| a.trim().to_string())
-        .collect())
-}
+    use super::*;
+    use crate::fixtures;
 
-#[derive(Debug)]
-struct ParseError {
-    cause: &'static str,
-}
+    fn parse_day<S: TimetableSource>(
+        source: S,
+        responses: &HashMap<String, String>,
+    ) -> Vec<TimeTableEntry> {
+        crate::day_page::parse_cell_ids(fixtures::DAY_PAGE)
+            .iter()
+            .filter_map(|cell_id| responses.get(cell_id))
+            .map(|response| source.parse_entry(response).unwrap())
+            .collect()
+    }
 
-impl Display for ParseError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        std::fmt::write(f, format_args!("Parse error: {}", self.cause))
+    #[test]
+    fn splits_records_by_utf16_length() {
+        let records = parse_delta(fixtures::DELTA_LECTURE).unwrap();
+        assert_eq!(records.len(), 7);
+        assert_eq!(records[1].kind, "updatePanel");
+        assert!(records[1].content.contains("Systemy operacyjne"));
+        assert_eq!(records[6].content, "Plan zajęć - PJATK");
+    }
+
+    #[test]
+    fn parses_the_same_entries_as_tooltip_html() {
+        let from_deltas = parse_day(AltApiDelta, &fixtures::day_page_deltas());
+        let from_tooltips = parse_day(TooltipHtml, &fixtures::day_page_tooltips());
+        assert_eq!(from_deltas.len(), 3);
+        assert_eq!(from_deltas, from_tooltips);
     }
-}
 
-impl Error for ParseError {
-    fn cause(&self) -> Option<&dyn Error> {
-        None
+    #[test]
+    fn rejects_truncated_delta() {
+        let truncated = &fixtures::DELTA_LECTURE[..200];
+        assert!(matches!(
+            AltApiDelta.parse_entry(truncated),
+            Err(ParseError::MalformedResponse { .. })
+        ));
     }
 }
@@ -0,0 +1,272 @@
+#![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    error::BuildError,
+    kind::EntryKind,
+    location::Location,
+    migration::SCHEMA_VERSION,
+    person::Person,
+    status::EntryStatus,
+    students::StudentsCount,
+    timetable::{parse_group_codes, TimeTableEntry},
+};
+
+/// Builds a `TimeTableEntry` outside of the scraper, e.g. for imports, custom events or tests.
+///
+/// Parsed fields (`tutors`, `kind`, `group_codes`, `students_count`, `location`) are derived
+/// from the raw ones the same way the scraper does it, and the id is derived from the content
+/// unless a plan cell is given with `source_id`.
+#[derive(Debug, Clone, Default)]
+pub struct TimeTableEntryBuilder {
+    source_id: Option<String>,
+    title: Option<String>,
+    persons: Vec<String>,
+    details: Option<String>,
+    type_of: String,
+    is_reservation: bool,
+    subjects: Vec<String>,
+    subject_codes: Vec<String>,
+    groups: Option<Vec<String>>,
+    students_count: Option<String>,
+    building: String,
+    room: String,
+    datetime_beginning: Option<DateTime<Utc>>,
+    datetime_ending: Option<DateTime<Utc>>,
+}
+
+impl TimeTableEntryBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Id of the plan cell, the id of entry is derived from it instead of the content
+    pub fn source_id(mut self, source_id: impl Into<String>) -> Self {
+        self.source_id = Some(source_id.into());
+        self
+    }
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+    /// Persons in the `Surname Name` order the plan uses
+    pub fn persons<I, S>(mut self, persons: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.persons = persons.into_iter().map(Into::into).collect();
+        self
+    }
+    pub fn details(mut self, details: impl Into<String>) -> Self {
+        self.details = Some(details.into());
+        self
+    }
+    /// Polish label of the type, e.g. `Wykład`
+    pub fn type_of(mut self, type_of: impl Into<String>) -> Self {
+        self.type_of = type_of.into();
+        self
+    }
+    /// Marks the entry as a room reservation rather than a class
+    pub fn reservation(mut self, is_reservation: bool) -> Self {
+        self.is_reservation = is_reservation;
+        self
+    }
+    pub fn subjects<I, S>(mut self, subjects: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.subjects = subjects.into_iter().map(Into::into).collect();
+        self
+    }
+    pub fn subject_codes<I, S>(mut self, subject_codes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.subject_codes = subject_codes.into_iter().map(Into::into).collect();
+        self
+    }
+    /// Groups as written in the plan, e.g. `WIs I.2 - 46c`
+    pub fn groups<I, S>(mut self, groups: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.groups = Some(groups.into_iter().map(Into::into).collect());
+        self
+    }
+    /// Count of students as written in the plan, e.g. `115 115 ITN`
+    pub fn students_count(mut self, students_count: impl Into<String>) -> Self {
+        self.students_count = Some(students_count.into());
+        self
+    }
+    pub fn building(mut self, building: impl Into<String>) -> Self {
+        self.building = building.into();
+        self
+    }
+    pub fn room(mut self, room: impl Into<String>) -> Self {
+        self.room = room.into();
+        self
+    }
+    pub fn datetime_beginning(mut self, datetime_beginning: DateTime<Utc>) -> Self {
+        self.datetime_beginning = Some(datetime_beginning);
+        self
+    }
+    pub fn datetime_ending(mut self, datetime_ending: DateTime<Utc>) -> Self {
+        self.datetime_ending = Some(datetime_ending);
+        self
+    }
+    /// Checks that the room isn't empty and that the entry ends after it begins
+    pub fn build(self) -> Result<TimeTableEntry, BuildError> {
+        let datetime_beginning = self.datetime_beginning.ok_or(BuildError::MissingField {
+            field: "datetime_beginning",
+        })?;
+        let datetime_ending = self.datetime_ending.ok_or(BuildError::MissingField {
+            field: "datetime_ending",
+        })?;
+        if datetime_ending <= datetime_beginning {
+            return Err(BuildError::EndBeforeStart {
+                beginning: datetime_beginning,
+                ending: datetime_ending,
+            });
+        }
+        if self.room.trim().is_empty() {
+            return Err(BuildError::EmptyRoom);
+        }
+        Ok(self.build_unchecked())
+    }
+    /// Builds the entry without checking it, for documents that were stored before the checks existed
+    pub(crate) fn build_unchecked(self) -> TimeTableEntry {
+        let datetime_beginning = self.datetime_beginning.unwrap_or_default();
+        let datetime_ending = self.datetime_ending.unwrap_or_default();
+        let room = self.room.trim().to_string();
+        let building = self.building.trim().to_string();
+        let entry = TimeTableEntry {
+            id: String::new(),
+            source_id: None,
+            status: EntryStatus::detect(self.title.as_deref(), self.details.as_deref()),
+            title: self.title,
+            tutors: self
+                .persons
+                .iter()
+                .filter_map(|person| Person::parse(person))
+                .collect(),
+            persons: self.persons,
+            details: self.details,
+            kind: EntryKind::from_label(&self.type_of, self.is_reservation),
+            type_of: self.type_of,
+            subjects: self.subjects,
+            subject_codes: self.subject_codes,
+            group_codes: parse_group_codes(self.groups.as_deref()),
+            groups: self.groups,
+            students_count: self
+                .students_count
+                .map(|students_count| StudentsCount::parse(&students_count)),
+            location: Location::parse(&building, &room),
+            building,
+            room,
+            datetime_beginning,
+            datetime_ending,
+            dst_adjustment: None,
+            parts: vec![],
+            local_time: None,
+            schema_version: SCHEMA_VERSION,
+        };
+        match self.source_id {
+            Some(source_id) => entry.with_source_id(&source_id),
+            None => entry.with_content_id(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+
+    fn beginning() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 4, 8, 30, 0).unwrap()
+    }
+
+    fn lecture() -> TimeTableEntryBuilder {
+        TimeTableEntry::builder()
+            .type_of("Wykład")
+            .persons(["dr inż. Niezgoda Adam"])
+            .subject_codes(["SOP"])
+            .building(" B2020 ")
+            .room("B/227 ")
+            .datetime_beginning(beginning())
+            .datetime_ending(beginning() + Duration::minutes(90))
+    }
+
+    #[test]
+    fn missing_beginning_is_reported() {
+        let builder = TimeTableEntryBuilder {
+            datetime_beginning: None,
+            ..lecture()
+        };
+        assert_eq!(
+            builder.build(),
+            Err(BuildError::MissingField {
+                field: "datetime_beginning"
+            })
+        );
+    }
+
+    #[test]
+    fn missing_ending_is_reported() {
+        let builder = TimeTableEntryBuilder {
+            datetime_ending: None,
+            ..lecture()
+        };
+        assert_eq!(
+            builder.build(),
+            Err(BuildError::MissingField {
+                field: "datetime_ending"
+            })
+        );
+    }
+
+    #[test]
+    fn ending_has_to_follow_beginning() {
+        for ending in [beginning(), beginning() - Duration::minutes(1)] {
+            assert_eq!(
+                lecture().datetime_ending(ending).build(),
+                Err(BuildError::EndBeforeStart {
+                    beginning: beginning(),
+                    ending
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn blank_room_is_rejected() {
+        assert_eq!(lecture().room("  ").build(), Err(BuildError::EmptyRoom));
+    }
+
+    #[test]
+    fn valid_entry_gets_derived_fields() {
+        let entry = lecture().build().unwrap();
+
+        assert_eq!(entry.get_building(), "B2020");
+        assert_eq!(entry.get_room(), "B/227");
+        assert_eq!(entry.get_location().floor, Some(2));
+        assert_eq!(entry.get_kind(), &EntryKind::Lecture);
+        assert_eq!(entry.get_tutors()[0].id(), "adam-niezgoda");
+        assert_eq!(entry.get_source_id(), None);
+        assert_eq!(entry.get_schema_version(), SCHEMA_VERSION);
+        assert_eq!(entry.get_id(), lecture().build().unwrap().get_id());
+    }
+
+    #[test]
+    fn source_id_gives_id_of_the_cell() {
+        let entry = lecture().source_id("1234;5678").build().unwrap();
+
+        assert_eq!(entry.get_source_id(), Some("1234;5678"));
+        assert_ne!(entry.get_id(), lecture().build().unwrap().get_id());
+    }
+}
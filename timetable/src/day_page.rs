@@ -0,0 +1,162 @@
+#![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
+
+use std::collections::HashMap;
+
+use kuchiki::{traits::TendrilSink, NodeRef};
+
+use crate::{
+    error::ParseError,
+    source::{TimetableSource, TooltipHtml},
+    timetable::TimeTableEntry,
+};
+
+/// Selector of every `ZajeciaTable` cell which opens a tooltip when clicked
+pub const ENTRY_CELL_SELECTOR: &str = "#ZajeciaTable tbody td[id*=\";\"]";
+
+/// Single entry cell of a PlanOgolny3 day page
+#[derive(Debug, Clone)]
+pub struct DayPageCell {
+    /// Id of the cell, the same one RadToolTipManager asks the tooltip for
+    pub cell_id: String,
+    /// Text shown inside of the cell
+    pub text: String,
+    /// Entry parsed from the tooltip of the cell, if the tooltip was provided
+    pub entry: Option<Result<TimeTableEntry, ParseError>>,
+}
+
+/// Parses a RadToolTip panel into an entry
+pub fn parse_tooltip(tooltip_html: &str) -> Result<TimeTableEntry, ParseError> {
+    TooltipHtml.parse_entry(tooltip_html)
+}
+
+/// Returns ids of all entry cells found in a saved day page, in document order
+pub fn parse_cell_ids(page_html: &str) -> Vec<String> {
+    let dom = kuchiki::parse_html().from_utf8().one(page_html.as_bytes());
+    get_cells(&dom)
+        .into_iter()
+        .map(|(cell_id, _)| cell_id)
+        .collect()
+}
+
+/// Parses a saved day page together with tooltips of its cells, keyed by cell id.
+///
+/// Cells without a tooltip are still returned, only without an entry.
+/// A tooltip which fails to parse doesn't stop the rest of the page from being parsed.
+pub fn parse_day_page(page_html: &str, tooltips: &HashMap<String, String>) -> Vec<DayPageCell> {
+    let dom = kuchiki::parse_html().from_utf8().one(page_html.as_bytes());
+    get_cells(&dom)
+        .into_iter()
+        .map(|(cell_id, text)| {
+            let entry = tooltips.get(&cell_id).map(|tooltip_html| {
+                parse_tooltip(tooltip_html).map(|entry| entry.with_source_id(&cell_id))
+            });
+            DayPageCell {
+                cell_id,
+                text,
+                entry,
+            }
+        })
+        .collect()
+}
+
+fn get_cells(dom: &NodeRef) -> Vec<(String, String)> {
+    if let Ok(cells) = dom.select(ENTRY_CELL_SELECTOR) {
+        cells
+            .filter_map(|cell| {
+                let cell_id = cell.attributes.borrow().get("id")?.to_string();
+                let text = cell
+                    .text_contents()
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                Some((cell_id, text))
+            })
+            .collect()
+    } else {
+        vec![]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+    use crate::{fixtures, kind::EntryKind, person::Person};
+
+    #[test]
+    fn finds_entry_cells_in_document_order() {
+        assert_eq!(
+            parse_cell_ids(fixtures::DAY_PAGE),
+            ["2413051;z", "2413087;z", "871204;r", "2413102;z"]
+        );
+    }
+
+    #[test]
+    fn parses_day_page_with_tooltips() {
+        let cells = parse_day_page(fixtures::DAY_PAGE, &fixtures::day_page_tooltips());
+        let texts: Vec<&str> = cells.iter().map(|cell| cell.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            [
+                "SOP Wykład",
+                "GUI Ćwiczenia",
+                "Egzamin poprawkowy",
+                "BYT Wykład"
+            ]
+        );
+
+        let lecture = cells[0].entry.clone().unwrap().unwrap();
+        assert_eq!(lecture.get_source_id(), Some("2413051;z"));
+        assert_eq!(lecture.get_kind(), &EntryKind::Lecture);
+        assert_eq!(lecture.get_subjects(), ["Systemy operacyjne"]);
+        assert_eq!(lecture.get_subject_codes(), ["SOP"]);
+        assert_eq!(
+            lecture.get_groups(),
+            Some(&["WIs I.2 - 46c".to_string(), "WIs I.2 - 23c".to_string()][..])
+        );
+        assert_eq!(lecture.get_tutors()[0].id(), Person::id_of("Niezgoda Adam"));
+        assert_eq!(lecture.get_building(), "A2020");
+        assert_eq!(lecture.get_room(), "A/152");
+        assert_eq!(
+            lecture.get_datetime_beginning(),
+            Utc.with_ymd_and_hms(2024, 10, 16, 6, 30, 0).unwrap()
+        );
+        assert_eq!(
+            lecture.get_datetime_ending(),
+            Utc.with_ymd_and_hms(2024, 10, 16, 8, 0, 0).unwrap()
+        );
+        assert_eq!(lecture.get_students_count().unwrap().enrolled, Some(115));
+        assert!(!lecture.get_status().cancelled());
+
+        let exercises = cells[1].entry.clone().unwrap().unwrap();
+        assert_eq!(exercises.get_kind(), &EntryKind::Exercises);
+        assert_eq!(exercises.get_tutors()[0].titles(), ["dr", "inż."]);
+        assert_eq!(exercises.get_students_count().unwrap().limit, Some(20));
+        assert!(exercises.get_status().cancelled());
+
+        let reservation = cells[2].entry.clone().unwrap().unwrap();
+        assert_eq!(reservation.get_kind(), &EntryKind::Exam);
+        assert_eq!(reservation.get_title(), Some("Egzamin poprawkowy"));
+        assert_eq!(reservation.get_groups(), None);
+        assert_eq!(reservation.get_persons(), ["Kowalski Jan"]);
+
+        assert_eq!(cells[3].cell_id, "2413102;z");
+        assert!(cells[3].entry.is_none());
+    }
+
+    #[test]
+    fn reports_tooltip_without_date() {
+        let tooltips = HashMap::from([(
+            "2413102;z".to_string(),
+            fixtures::TOOLTIP_MISSING_DATE.to_string(),
+        )]);
+        let cells = parse_day_page(fixtures::DAY_PAGE, &tooltips);
+        let error = cells[3].entry.clone().unwrap().unwrap_err();
+        assert!(matches!(
+            error,
+            ParseError::MissingSelector { field: "date", .. }
+        ));
+        assert!(cells[..3].iter().all(|cell| cell.entry.is_none()));
+    }
+}
@@ -0,0 +1,122 @@
+#![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
+
+use std::{error::Error, fmt::Display};
+
+use chrono::{DateTime, Utc};
+
+/// Reason why a tooltip could not be turned into an entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// None of the selectors matched any element
+    MissingSelector {
+        selector: &'static str,
+        field: &'static str,
+    },
+    /// Date of the entry is not in `dd.mm.YYYY` format
+    BadDateFormat { selector: &'static str, raw: String },
+    /// Hour of the entry is not in `HH:MM:SS` format
+    BadTimeFormat { selector: &'static str, raw: String },
+    /// Local time does not map to a single point in time in Europe/Warsaw
+    AmbiguousLocalTime { selector: &'static str, raw: String },
+    /// Response wrapping the tooltip is not in the expected format
+    MalformedResponse { selector: &'static str, raw: String },
+    /// Entry ends before (or exactly when) it begins
+    EndBeforeStart {
+        selector: &'static str,
+        raw: String,
+        beginning: String,
+    },
+}
+
+impl ParseError {
+    /// Selector of the element the parser failed on
+    pub fn selector(&self) -> &'static str {
+        match self {
+            ParseError::MissingSelector { selector, .. }
+            | ParseError::BadDateFormat { selector, .. }
+            | ParseError::BadTimeFormat { selector, .. }
+            | ParseError::AmbiguousLocalTime { selector, .. }
+            | ParseError::MalformedResponse { selector, .. }
+            | ParseError::EndBeforeStart { selector, .. } => selector,
+        }
+    }
+    /// Raw text the parser failed on, if the element was found
+    pub fn raw(&self) -> Option<&str> {
+        match self {
+            ParseError::MissingSelector { .. } => None,
+            ParseError::BadDateFormat { raw, .. }
+            | ParseError::BadTimeFormat { raw, .. }
+            | ParseError::AmbiguousLocalTime { raw, .. }
+            | ParseError::MalformedResponse { raw, .. }
+            | ParseError::EndBeforeStart { raw, .. } => Some(raw),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::MissingSelector { selector, field } => {
+                write!(f, "Parse error: missing {field} (`{selector}`)")
+            }
+            ParseError::BadDateFormat { selector, raw } => {
+                write!(f, "Parse error: bad date format `{raw}` (`{selector}`)")
+            }
+            ParseError::BadTimeFormat { selector, raw } => {
+                write!(f, "Parse error: bad time format `{raw}` (`{selector}`)")
+            }
+            ParseError::AmbiguousLocalTime { selector, raw } => {
+                write!(
+                    f,
+                    "Parse error: `{raw}` is not a single local time in Europe/Warsaw (`{selector}`)"
+                )
+            }
+            ParseError::MalformedResponse { selector, raw } => {
+                write!(f, "Parse error: malformed response `{raw}` (`{selector}`)")
+            }
+            ParseError::EndBeforeStart {
+                selector,
+                raw,
+                beginning,
+            } => {
+                write!(
+                    f,
+                    "Parse error: ending `{raw}` is not after beginning `{beginning}` (`{selector}`)"
+                )
+            }
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// Reason why a `TimeTableEntryBuilder` could not build an entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    /// Required field was never set
+    MissingField { field: &'static str },
+    /// Room is empty or whitespace only
+    EmptyRoom,
+    /// Entry ends before (or exactly when) it begins
+    EndBeforeStart {
+        beginning: DateTime<Utc>,
+        ending: DateTime<Utc>,
+    },
+}
+
+impl Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildError::MissingField { field } => write!(f, "Build error: missing {field}"),
+            BuildError::EmptyRoom => write!(f, "Build error: room is empty"),
+            BuildError::EndBeforeStart { beginning, ending } => {
+                write!(
+                    f,
+                    "Build error: ending `{ending}` is not after beginning `{beginning}`"
+                )
+            }
+        }
+    }
+}
+
+impl Error for BuildError {}
@@ -0,0 +1,544 @@
+#![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
+
+use std::collections::BTreeSet;
+
+use bson::{doc, Bson, Document};
+use chrono::{DateTime, Utc};
+use poem_openapi::Enum;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    group::{GroupCode, StudyMode},
+    kind::EntryKind,
+    person::Person,
+};
+
+/// How entries have to match a list of groups or tutors
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Enum)]
+#[serde(rename_all = "snake_case")]
+#[oai(rename_all = "snake_case")]
+pub enum ListMatch {
+    /// Entries of any of them
+    #[default]
+    Any,
+    /// Only entries shared by all of them
+    All,
+}
+
+/// How entries have to fit into the date range
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Enum)]
+#[serde(rename_all = "snake_case")]
+#[oai(rename_all = "snake_case")]
+pub enum RangeMode {
+    /// Entries taking place at any moment of the range, including those spanning its ends
+    #[default]
+    Overlap,
+    /// Only entries beginning and ending within the range
+    Contained,
+}
+
+/// Conditions entries have to meet, compiled into a single MongoDB query by `to_document`.
+///
+/// Every condition that is set has to hold. Conditions taking a list hold when any of their
+/// values does, e.g. entries of either of two groups, but only those led by one of the tutors
+/// when tutors are set as well. Groups and tutors can be required all at once with `list_match`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct TimetableFilter {
+    date_from: Option<DateTime<Utc>>,
+    date_to: Option<DateTime<Utc>>,
+    range_mode: RangeMode,
+    groups: Vec<String>,
+    tutors: Vec<String>,
+    subjects: Vec<String>,
+    subject_codes: Vec<String>,
+    rooms: Vec<String>,
+    buildings: Vec<String>,
+    kinds: Vec<EntryKind>,
+    list_match: ListMatch,
+    min_students: Option<u32>,
+    faculty: Option<String>,
+    study_mode: Option<StudyMode>,
+    degree: Option<u8>,
+    semester: Option<u8>,
+    group_number: Option<u32>,
+    remote: Option<bool>,
+    hide_cancelled: bool,
+}
+
+impl TimetableFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Beginning of the date range
+    pub fn date_from(mut self, date_from: DateTime<Utc>) -> Self {
+        self.date_from = Some(date_from);
+        self
+    }
+    /// End of the date range
+    pub fn date_to(mut self, date_to: DateTime<Utc>) -> Self {
+        self.date_to = Some(date_to);
+        self
+    }
+    /// Whether entries have to overlap the date range or be contained in it
+    pub fn range_mode(mut self, range_mode: RangeMode) -> Self {
+        self.range_mode = range_mode;
+        self
+    }
+    /// Groups in any spelling
+    pub fn groups<I, S>(mut self, groups: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.groups = groups.into_iter().map(Into::into).collect();
+        self
+    }
+    /// Tutors as any spelling of their name or as their id
+    pub fn tutors<I, S>(mut self, tutors: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tutors = tutors.into_iter().map(Into::into).collect();
+        self
+    }
+    /// Names of subjects, e.g. `Systemy operacyjne`
+    pub fn subjects<I, S>(mut self, subjects: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.subjects = subjects.into_iter().map(Into::into).collect();
+        self
+    }
+    /// Codes of subjects, e.g. `SOP`
+    pub fn subject_codes<I, S>(mut self, subject_codes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.subject_codes = subject_codes.into_iter().map(Into::into).collect();
+        self
+    }
+    /// Rooms as shown in the plan, e.g. `B/227`
+    pub fn rooms<I, S>(mut self, rooms: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.rooms = rooms.into_iter().map(Into::into).collect();
+        self
+    }
+    /// Buildings as shown in the plan, e.g. `B2020`
+    pub fn buildings<I, S>(mut self, buildings: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.buildings = buildings.into_iter().map(Into::into).collect();
+        self
+    }
+    pub fn kinds(mut self, kinds: impl IntoIterator<Item = EntryKind>) -> Self {
+        self.kinds = kinds.into_iter().collect();
+        self
+    }
+    /// Whether groups and tutors are alternatives or all required
+    pub fn list_match(mut self, list_match: ListMatch) -> Self {
+        self.list_match = list_match;
+        self
+    }
+    /// Only entries with more than this many enrolled students
+    pub fn min_students(mut self, min_students: u32) -> Self {
+        self.min_students = Some(min_students);
+        self
+    }
+    /// Only groups of this faculty, e.g. `WI`
+    pub fn faculty(mut self, faculty: impl Into<String>) -> Self {
+        self.faculty = Some(faculty.into());
+        self
+    }
+    pub fn study_mode(mut self, study_mode: StudyMode) -> Self {
+        self.study_mode = Some(study_mode);
+        self
+    }
+    /// Only groups of this degree level, `1` or `2`
+    pub fn degree(mut self, degree: u8) -> Self {
+        self.degree = Some(degree);
+        self
+    }
+    pub fn semester(mut self, semester: u8) -> Self {
+        self.semester = Some(semester);
+        self
+    }
+    pub fn group_number(mut self, group_number: u32) -> Self {
+        self.group_number = Some(group_number);
+        self
+    }
+    /// Only remote (`true`) or only on-site (`false`) entries
+    pub fn remote(mut self, remote: bool) -> Self {
+        self.remote = Some(remote);
+        self
+    }
+    /// Leaves out entries announced as cancelled
+    pub fn hide_cancelled(mut self, hide_cancelled: bool) -> Self {
+        self.hide_cancelled = hide_cancelled;
+        self
+    }
+
+    pub fn get_date_from(&self) -> Option<DateTime<Utc>> {
+        self.date_from
+    }
+    pub fn get_date_to(&self) -> Option<DateTime<Utc>> {
+        self.date_to
+    }
+
+    /// MongoDB query matching the entries, an empty one when nothing is set
+    pub fn to_document(&self) -> Document {
+        let mut conditions: Vec<Document> = vec![];
+        conditions.extend(self.date_conditions());
+        conditions.extend(self.list_conditions(&self.groups, |groups| {
+            let normalised: Vec<String> = groups
+                .iter()
+                .map(|group| GroupCode::normalise(group))
+                .collect();
+            doc! {"$or": [
+                {"groups": {"$in": groups}},
+                {"group_codes.code": {"$in": normalised}},
+            ]}
+        }));
+        conditions.extend(self.list_conditions(&self.tutors, |tutors| {
+            let ids: Vec<String> = tutors.iter().map(|tutor| Person::id_of(tutor)).collect();
+            doc! {"$or": [
+                {"persons": {"$in": tutors}},
+                {"tutors.id": {"$in": ids}},
+            ]}
+        }));
+        if !self.subjects.is_empty() {
+            conditions.push(doc! {"subjects": {"$in": unique(&self.subjects)}});
+        }
+        if !self.subject_codes.is_empty() {
+            conditions.push(doc! {"subject_codes": {"$in": unique(&self.subject_codes)}});
+        }
+        if !self.rooms.is_empty() {
+            conditions.push(doc! {"room": {"$in": unique(&self.rooms)}});
+        }
+        if !self.buildings.is_empty() {
+            conditions.push(doc! {"building": {"$in": unique(&self.buildings)}});
+        }
+        if !self.kinds.is_empty() {
+            let mut kinds: Vec<&EntryKind> = self.kinds.iter().collect();
+            kinds.sort_by_key(|kind| kind.as_str());
+            kinds.dedup_by_key(|kind| kind.as_str());
+            let kinds: Vec<Bson> = kinds.into_iter().map(EntryKind::to_query_value).collect();
+            conditions.push(doc! {"kind": {"$in": kinds}});
+        }
+        if let Some(min_students) = self.min_students {
+            conditions.push(doc! {"students_count.enrolled": {"$gt": min_students}});
+        }
+        if let Some(remote) = self.remote {
+            if remote {
+                conditions.push(doc! {"location.virtual_location": {"$ne": null}});
+            } else {
+                conditions.push(doc! {"location.virtual_location": null});
+            }
+        }
+        if self.hide_cancelled {
+            conditions.push(doc! {"status.cancelled": {"$ne": true}});
+        }
+        let group_code = self.group_code_conditions();
+        if !group_code.is_empty() {
+            conditions.push(doc! {"group_codes": {"$elemMatch": group_code}});
+        }
+        match conditions.len() {
+            0 => doc! {},
+            1 => conditions.remove(0),
+            _ => doc! {"$and": conditions},
+        }
+    }
+
+    /// Conditions of the date range, an entry overlaps it when it ends after its beginning and begins before its end
+    fn date_conditions(&self) -> Vec<Document> {
+        let (from_field, from_operator, to_field, to_operator) = match self.range_mode {
+            RangeMode::Overlap => ("datetime_ending", "$gt", "datetime_beginning", "$lt"),
+            RangeMode::Contained => ("datetime_beginning", "$gte", "datetime_ending", "$lte"),
+        };
+        let mut conditions = vec![];
+        if let Some(date_from) = self.date_from {
+            let date_from = Bson::DateTime(bson::DateTime::from_chrono(date_from));
+            conditions.push(doc! {from_field: {from_operator: date_from}});
+        }
+        if let Some(date_to) = self.date_to {
+            let date_to = Bson::DateTime(bson::DateTime::from_chrono(date_to));
+            conditions.push(doc! {to_field: {to_operator: date_to}});
+        }
+        conditions
+    }
+
+    /// Conditions of a list of groups or tutors, one for the whole list or one per value
+    fn list_conditions(
+        &self,
+        values: &[String],
+        condition: impl Fn(Vec<&str>) -> Document,
+    ) -> Vec<Document> {
+        let values = unique(values);
+        if values.is_empty() {
+            return vec![];
+        }
+        match self.list_match {
+            ListMatch::Any => vec![condition(values)],
+            ListMatch::All => values
+                .into_iter()
+                .map(|value| condition(vec![value]))
+                .collect(),
+        }
+    }
+
+    /// Conditions a single group of an entry has to meet
+    fn group_code_conditions(&self) -> Document {
+        let mut conditions = doc! {};
+        if let Some(faculty) = &self.faculty {
+            conditions.insert("faculty", faculty.to_uppercase());
+        }
+        if let Some(study_mode) = &self.study_mode {
+            conditions.insert(
+                "study_mode",
+                bson::to_bson(study_mode).expect("Study mode serialization failed!"),
+            );
+        }
+        if let Some(degree) = self.degree {
+            conditions.insert("degree", i32::from(degree));
+        }
+        if let Some(semester) = self.semester {
+            conditions.insert("semester", i32::from(semester));
+        }
+        if let Some(group_number) = self.group_number {
+            conditions.insert("number", group_number);
+        }
+        conditions
+    }
+}
+
+/// Values without repetitions, sorted so equal filters give equal queries
+fn unique<S: AsRef<str>>(values: &[S]) -> Vec<&str> {
+    values
+        .iter()
+        .map(AsRef::as_ref)
+        .collect::<BTreeSet<&str>>()
+        .into_iter()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use bson::Regex;
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn date(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 11, day, 0, 0, 0).unwrap()
+    }
+
+    fn bson_date(day: u32) -> Bson {
+        Bson::DateTime(bson::DateTime::from_chrono(date(day)))
+    }
+
+    #[test]
+    fn empty_filter_matches_everything() {
+        assert_eq!(TimetableFilter::new().to_document(), doc! {});
+    }
+
+    #[test]
+    fn groups_and_tutors_are_and_ed() {
+        let filter = TimetableFilter::new()
+            .groups(["WIs I.2 - 46c"])
+            .tutors(["Niezgoda Adam"]);
+        assert_eq!(
+            filter.to_document(),
+            doc! {"$and": [
+                {"$or": [
+                    {"groups": {"$in": ["WIs I.2 - 46c"]}},
+                    {"group_codes.code": {"$in": ["WIs I.2 - 46c"]}},
+                ]},
+                {"$or": [
+                    {"persons": {"$in": ["Niezgoda Adam"]}},
+                    {"tutors.id": {"$in": ["adam-niezgoda"]}},
+                ]},
+            ]}
+        );
+    }
+
+    #[test]
+    fn any_of_the_list_is_one_condition() {
+        let filter = TimetableFilter::new().groups(["WIs I.2 - 46c", "WIS I.2 - 23c"]);
+        assert_eq!(
+            filter.to_document(),
+            doc! {"$or": [
+                {"groups": {"$in": ["WIS I.2 - 23c", "WIs I.2 - 46c"]}},
+                {"group_codes.code": {"$in": ["WIs I.2 - 23c", "WIs I.2 - 46c"]}},
+            ]}
+        );
+    }
+
+    #[test]
+    fn all_of_the_list_is_one_condition_per_value() {
+        let filter = TimetableFilter::new()
+            .groups(["WIs I.2 - 46c", "WIs I.2 - 23c", "WIs I.2 - 46c"])
+            .list_match(ListMatch::All);
+        assert_eq!(
+            filter.to_document(),
+            doc! {"$and": [
+                {"$or": [
+                    {"groups": {"$in": ["WIs I.2 - 23c"]}},
+                    {"group_codes.code": {"$in": ["WIs I.2 - 23c"]}},
+                ]},
+                {"$or": [
+                    {"groups": {"$in": ["WIs I.2 - 46c"]}},
+                    {"group_codes.code": {"$in": ["WIs I.2 - 46c"]}},
+                ]},
+            ]}
+        );
+    }
+
+    #[test]
+    fn tutors_match_names_or_ids() {
+        let filter = TimetableFilter::new().tutors(["dr inż. Tomaszewski Michał"]);
+        assert_eq!(
+            filter.to_document(),
+            doc! {"$or": [
+                {"persons": {"$in": ["dr inż. Tomaszewski Michał"]}},
+                {"tutors.id": {"$in": ["michal-tomaszewski"]}},
+            ]}
+        );
+    }
+
+    #[test]
+    fn subjects_are_unique() {
+        let filter = TimetableFilter::new()
+            .subjects(["Systemy operacyjne", "Bazy danych", "Systemy operacyjne"])
+            .subject_codes(["SOP", "BYT"]);
+        assert_eq!(
+            filter.to_document(),
+            doc! {"$and": [
+                {"subjects": {"$in": ["Bazy danych", "Systemy operacyjne"]}},
+                {"subject_codes": {"$in": ["BYT", "SOP"]}},
+            ]}
+        );
+    }
+
+    #[test]
+    fn rooms_and_buildings_are_and_ed() {
+        let filter = TimetableFilter::new()
+            .rooms(["B/227", "A/152"])
+            .buildings(["B2020"]);
+        assert_eq!(
+            filter.to_document(),
+            doc! {"$and": [
+                {"room": {"$in": ["A/152", "B/227"]}},
+                {"building": {"$in": ["B2020"]}},
+            ]}
+        );
+    }
+
+    #[test]
+    fn unknown_kinds_match_any_label() {
+        let filter = TimetableFilter::new().kinds([
+            EntryKind::Lecture,
+            EntryKind::Unknown("Lektorat".to_string()),
+            EntryKind::Exercises,
+            EntryKind::Unknown(String::new()),
+            EntryKind::Lecture,
+        ]);
+        assert_eq!(
+            filter.to_document(),
+            doc! {"kind": {"$in": [
+                "exercises",
+                "lecture",
+                Bson::RegularExpression(Regex {
+                    pattern: "^unknown(:|$)".to_string(),
+                    options: String::new(),
+                }),
+            ]}}
+        );
+    }
+
+    #[test]
+    fn min_students_is_exclusive() {
+        let filter = TimetableFilter::new().min_students(30);
+        assert_eq!(
+            filter.to_document(),
+            doc! {"students_count.enrolled": {"$gt": 30}}
+        );
+    }
+
+    #[test]
+    fn remote_checks_virtual_location() {
+        assert_eq!(
+            TimetableFilter::new().remote(true).to_document(),
+            doc! {"location.virtual_location": {"$ne": null}}
+        );
+        assert_eq!(
+            TimetableFilter::new().remote(false).to_document(),
+            doc! {"location.virtual_location": null}
+        );
+    }
+
+    #[test]
+    fn hide_cancelled_keeps_entries_without_status() {
+        assert_eq!(
+            TimetableFilter::new().hide_cancelled(true).to_document(),
+            doc! {"status.cancelled": {"$ne": true}}
+        );
+        assert_eq!(
+            TimetableFilter::new().hide_cancelled(false).to_document(),
+            doc! {}
+        );
+    }
+
+    #[test]
+    fn group_code_parts_match_a_single_group() {
+        let filter = TimetableFilter::new()
+            .faculty("wis")
+            .study_mode(StudyMode::PartTime)
+            .degree(1)
+            .semester(2)
+            .group_number(46);
+        assert_eq!(
+            filter.to_document(),
+            doc! {"group_codes": {"$elemMatch": {
+                "faculty": "WIS",
+                "study_mode": "part_time",
+                "degree": 1,
+                "semester": 2,
+                "number": 46,
+            }}}
+        );
+    }
+
+    #[test]
+    fn overlapping_entries_end_after_beginning_and_begin_before_end() {
+        let filter = TimetableFilter::new().date_from(date(4)).date_to(date(11));
+        assert_eq!(
+            filter.to_document(),
+            doc! {"$and": [
+                {"datetime_ending": {"$gt": bson_date(4)}},
+                {"datetime_beginning": {"$lt": bson_date(11)}},
+            ]}
+        );
+    }
+
+    #[test]
+    fn contained_entries_begin_and_end_within_range() {
+        let filter = TimetableFilter::new()
+            .date_from(date(4))
+            .date_to(date(11))
+            .range_mode(RangeMode::Contained);
+        assert_eq!(
+            filter.to_document(),
+            doc! {"$and": [
+                {"datetime_beginning": {"$gte": bson_date(4)}},
+                {"datetime_ending": {"$lte": bson_date(11)}},
+            ]}
+        );
+    }
+}
@@ -0,0 +1,74 @@
+#![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::timetable::TimeTableEntry;
+
+/// Saved PlanOgolny3 page of 16.10.2024 with four entry cells
+pub const DAY_PAGE: &str = include_str!("../tests/fixtures/day_page.html");
+/// Tooltip of cell `2413051;z`, a lecture
+pub const TOOLTIP_LECTURE: &str = include_str!("../tests/fixtures/tooltip_lecture.html");
+/// Tooltip of cell `2413087;z`, cancelled exercises
+pub const TOOLTIP_EXERCISES: &str = include_str!("../tests/fixtures/tooltip_exercises.html");
+/// Tooltip of cell `871204;r`, an exam reservation
+pub const TOOLTIP_RESERVATION: &str = include_str!("../tests/fixtures/tooltip_reservation.html");
+/// Tooltip cut off before its date
+pub const TOOLTIP_MISSING_DATE: &str = include_str!("../tests/fixtures/tooltip_missing_date.html");
+
+/// Tooltip callback response of cell `2413051;z`
+pub const DELTA_LECTURE: &str = include_str!("../tests/fixtures/delta_lecture.txt");
+/// Tooltip callback response of cell `2413087;z`
+pub const DELTA_EXERCISES: &str = include_str!("../tests/fixtures/delta_exercises.txt");
+/// Tooltip callback response of cell `871204;r`
+pub const DELTA_RESERVATION: &str = include_str!("../tests/fixtures/delta_reservation.txt");
+
+/// Tooltips of `DAY_PAGE` keyed by cell id, the last cell has none
+pub fn day_page_tooltips() -> HashMap<String, String> {
+    by_cell_id([TOOLTIP_LECTURE, TOOLTIP_EXERCISES, TOOLTIP_RESERVATION])
+}
+
+/// Tooltip callback responses of `DAY_PAGE` keyed by cell id, the last cell has none
+pub fn day_page_deltas() -> HashMap<String, String> {
+    by_cell_id([DELTA_LECTURE, DELTA_EXERCISES, DELTA_RESERVATION])
+}
+
+fn by_cell_id(responses: [&str; 3]) -> HashMap<String, String> {
+    ["2413051;z", "2413087;z", "871204;r"]
+        .into_iter()
+        .zip(responses)
+        .map(|(cell_id, response)| (cell_id.to_string(), response.to_string()))
+        .collect()
+}
+
+/// Sample lecture starting now
+pub fn mock_entry() -> TimeTableEntry {
+    mock_entry_at(Utc::now())
+}
+
+/// Sample two hour lecture starting at `datetime_beginning`
+pub fn mock_entry_at(datetime_beginning: DateTime<Utc>) -> TimeTableEntry {
+    TimeTableEntry::builder()
+        .title("Ostatni wykład")
+        .persons(["Niezgoda Adam", "Tomaszewski Michał"])
+        .details("Podsumowanie semestru")
+        .type_of("Wykład")
+        .subjects(["Systemy operacyjne", "Programowanie obiektowe i GUI"])
+        .subject_codes(["SOP", "GUI"])
+        .groups(["WIs I.2 - 46c", "WIS I.2 - 23c"])
+        .students_count("115 115 ITN")
+        .building("B2020")
+        .room("B/227")
+        .datetime_beginning(datetime_beginning)
+        .datetime_ending(datetime_beginning + Duration::hours(2))
+        .build()
+        .expect("Mock entry is valid")
+}
+
+/// `count` sample lectures, one a week starting at `first_beginning`
+pub fn mock_entries(first_beginning: DateTime<Utc>, count: usize) -> Vec<TimeTableEntry> {
+    (0..count)
+        .map(|week| mock_entry_at(first_beginning + Duration::weeks(week as i64)))
+        .collect()
+}
@@ -0,0 +1,261 @@
+#![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
+
+use std::fmt::Display;
+
+use poem_openapi::{Enum, Object};
+use serde::{Deserialize, Serialize};
+
+/// Mode of studies, encoded by the letter following the faculty
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Enum)]
+#[serde(rename_all = "snake_case")]
+#[oai(rename_all = "snake_case")]
+pub enum StudyMode {
+    /// `s` - stacjonarne
+    Stationary,
+    /// `n` - niestacjonarne
+    PartTime,
+    /// `i` - internetowe
+    Remote,
+}
+
+impl StudyMode {
+    fn from_letter(letter: char) -> Option<Self> {
+        match letter {
+            's' => Some(StudyMode::Stationary),
+            'n' => Some(StudyMode::PartTime),
+            'i' => Some(StudyMode::Remote),
+            _ => None,
+        }
+    }
+    fn letter(self) -> char {
+        match self {
+            StudyMode::Stationary => 's',
+            StudyMode::PartTime => 'n',
+            StudyMode::Remote => 'i',
+        }
+    }
+}
+
+/// Student group name split into its parts, e.g. `WIs I.2 - 46c`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Object)]
+pub struct GroupCode {
+    /// Normalised name of the group
+    pub(crate) code: String,
+    /// Faculty, e.g. `WI`
+    pub(crate) faculty: String,
+    /// Mode of studies
+    pub(crate) study_mode: Option<StudyMode>,
+    /// Degree level, `1` for `I`, `2` for `II`
+    pub(crate) degree: u8,
+    /// Semester
+    pub(crate) semester: u8,
+    /// Number of the group
+    pub(crate) number: u32,
+    /// Class type suffix, e.g. `c`
+    pub(crate) class_type: Option<String>,
+}
+
+impl GroupCode {
+    /// Parses a group name, returns `None` when it doesn't follow the `WIs I.2 - 46c` pattern
+    pub fn parse(raw: &str) -> Option<Self> {
+        let (prefix, suffix) = raw.split_once('-')?;
+        let mut prefix = prefix.split_whitespace();
+        let (faculty, study_mode) = parse_faculty(prefix.next()?)?;
+        let (degree, semester) = prefix.next()?.split_once('.')?;
+        if prefix.next().is_some() {
+            return None;
+        }
+        let degree = parse_roman(degree)?;
+        let semester = semester.parse().ok()?;
+        let suffix = suffix.trim();
+        let digits_end = suffix
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(suffix.len());
+        let number = suffix[..digits_end].parse().ok()?;
+        let class_type = suffix[digits_end..].trim().to_lowercase();
+        let mut group = Self {
+            code: String::new(),
+            faculty,
+            study_mode,
+            degree,
+            semester,
+            number,
+            class_type: if class_type.is_empty() {
+                None
+            } else {
+                Some(class_type)
+            },
+        };
+        group.code = group.to_string();
+        Some(group)
+    }
+    /// Returns the group name with normalised casing, falling back to the trimmed input
+    pub fn normalise(raw: &str) -> String {
+        GroupCode::parse(raw)
+            .map(|group| group.code)
+            .unwrap_or_else(|| raw.trim().to_string())
+    }
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+    pub fn faculty(&self) -> &str {
+        &self.faculty
+    }
+    pub fn study_mode(&self) -> Option<StudyMode> {
+        self.study_mode
+    }
+    pub fn degree(&self) -> u8 {
+        self.degree
+    }
+    pub fn semester(&self) -> u8 {
+        self.semester
+    }
+    pub fn number(&self) -> u32 {
+        self.number
+    }
+    pub fn class_type(&self) -> Option<&str> {
+        self.class_type.as_deref()
+    }
+}
+
+impl Display for GroupCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.faculty)?;
+        if let Some(study_mode) = self.study_mode {
+            write!(f, "{}", study_mode.letter())?;
+        }
+        write!(
+            f,
+            " {}.{} - {}{}",
+            "I".repeat(self.degree.into()),
+            self.semester,
+            self.number,
+            self.class_type.as_deref().unwrap_or_default()
+        )
+    }
+}
+
+/// Splits `WIs` into faculty and mode of studies.
+///
+/// A lowercase last letter is always the mode, an uppercase one only when it's `S` or `N`,
+/// as faculties like `WZI` end with an uppercase `I`.
+fn parse_faculty(prefix: &str) -> Option<(String, Option<StudyMode>)> {
+    if prefix.is_empty() || !prefix.chars().all(char::is_alphabetic) {
+        return None;
+    }
+    let last = prefix.chars().last()?;
+    let study_mode = if last.is_lowercase() {
+        StudyMode::from_letter(last)
+    } else {
+        match last {
+            'S' | 'N' => StudyMode::from_letter(last.to_ascii_lowercase()),
+            _ => None,
+        }
+    };
+    let faculty = if study_mode.is_some() {
+        &prefix[..prefix.len() - last.len_utf8()]
+    } else {
+        prefix
+    };
+    if faculty.is_empty() {
+        None
+    } else {
+        Some((faculty.to_uppercase(), study_mode))
+    }
+}
+
+fn parse_roman(degree: &str) -> Option<u8> {
+    match degree.to_uppercase().as_str() {
+        "I" => Some(1),
+        "II" => Some(2),
+        "III" => Some(3),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_stationary_group() {
+        let group = GroupCode::parse("WIs I.2 - 46c").unwrap();
+        assert_eq!(group.code(), "WIs I.2 - 46c");
+        assert_eq!(group.faculty(), "WI");
+        assert_eq!(group.study_mode(), Some(StudyMode::Stationary));
+        assert_eq!(group.degree(), 1);
+        assert_eq!(group.semester(), 2);
+        assert_eq!(group.number(), 46);
+        assert_eq!(group.class_type(), Some("c"));
+    }
+
+    #[test]
+    fn parses_part_time_and_remote_groups() {
+        let part_time = GroupCode::parse("WIn II.3 - 12w").unwrap();
+        assert_eq!(part_time.study_mode(), Some(StudyMode::PartTime));
+        assert_eq!(part_time.degree(), 2);
+        assert_eq!(part_time.class_type(), Some("w"));
+
+        let remote = GroupCode::parse("WIi I.1 - 1").unwrap();
+        assert_eq!(remote.study_mode(), Some(StudyMode::Remote));
+        assert_eq!(remote.class_type(), None);
+        assert_eq!(remote.code(), "WIi I.1 - 1");
+    }
+
+    #[test]
+    fn parses_other_faculties() {
+        let group = GroupCode::parse("WZI I.4 - 7l").unwrap();
+        assert_eq!(group.faculty(), "WZI");
+        assert_eq!(group.study_mode(), None);
+        assert_eq!(group.code(), "WZI I.4 - 7l");
+
+        let group = GroupCode::parse("WSKs III.1 - 3").unwrap();
+        assert_eq!(group.faculty(), "WSK");
+        assert_eq!(group.degree(), 3);
+    }
+
+    #[test]
+    fn normalises_casing_and_spacing() {
+        for raw in ["WIS I.2 - 46C", "wis i.2-46c", "  WIs  I.2 -  46 c "] {
+            assert_eq!(GroupCode::normalise(raw), "WIs I.2 - 46c", "{raw}");
+        }
+        assert_eq!(GroupCode::normalise(" Grupa X "), "Grupa X");
+    }
+
+    #[test]
+    fn rejects_malformed_groups() {
+        for raw in [
+            "",
+            "Grupa 1",
+            "WIs I.2",
+            "WIs 2.2 - 46c",
+            "WIs IV.2 - 46c",
+            "WIs I.x - 46c",
+            "WIs I.2 - c",
+            "WI1s I.2 - 46c",
+            "s I.2 - 46c",
+            "WIs I.2 dodatkowa - 46c",
+        ] {
+            assert_eq!(GroupCode::parse(raw), None, "{raw}");
+        }
+    }
+
+    #[test]
+    fn faculty_ends_with_mode_only_when_it_can() {
+        assert_eq!(
+            parse_faculty("WIs"),
+            Some(("WI".to_string(), Some(StudyMode::Stationary)))
+        );
+        assert_eq!(
+            parse_faculty("WIN"),
+            Some(("WI".to_string(), Some(StudyMode::PartTime)))
+        );
+        assert_eq!(parse_faculty("WZI"), Some(("WZI".to_string(), None)));
+        assert_eq!(
+            parse_faculty("WZi"),
+            Some(("WZ".to_string(), Some(StudyMode::Remote)))
+        );
+        assert_eq!(parse_faculty("n"), None);
+        assert_eq!(parse_faculty("W1"), None);
+    }
+}
@@ -0,0 +1,277 @@
+#![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
+
+use std::time::UNIX_EPOCH;
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Europe::Warsaw;
+
+use crate::{
+    series::Series,
+    timetable::{hash_id, TimeTableEntry},
+};
+
+/// Domain part of event UIDs, keeps them unique across calendars
+const UID_DOMAIN: &str = "sigma.pjatk21";
+
+/// Europe/Warsaw with the EU rules in force since 1996
+const VTIMEZONE: [&str; 18] = [
+    "BEGIN:VTIMEZONE",
+    "TZID:Europe/Warsaw",
+    "X-LIC-LOCATION:Europe/Warsaw",
+    "BEGIN:DAYLIGHT",
+    "TZOFFSETFROM:+0100",
+    "TZOFFSETTO:+0200",
+    "TZNAME:CEST",
+    "DTSTART:19700329T020000",
+    "RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU",
+    "END:DAYLIGHT",
+    "BEGIN:STANDARD",
+    "TZOFFSETFROM:+0200",
+    "TZOFFSETTO:+0100",
+    "TZNAME:CET",
+    "DTSTART:19701025T030000",
+    "RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU",
+    "END:STANDARD",
+    "END:VTIMEZONE",
+];
+
+/// RFC 5545 calendar with one event per entry
+pub fn entries_calendar(name: &str, entries: &[TimeTableEntry], stamp: DateTime<Utc>) -> String {
+    let mut calendar = CalendarWriter::new(Some(name));
+    for entry in entries {
+        let uid = entry_uid(entry.get_id());
+        calendar.event(entry, span(entry), &uid, stamp, |_| {});
+    }
+    calendar.finish()
+}
+
+/// RFC 5545 calendar with one recurring event per series, plus an event for every override.
+///
+/// The recurring event starts with the first occurrence even when that one is an override, its
+/// other properties come from the most common variant in `Series::entry`.
+pub fn series_calendar(name: &str, series: &[Series], stamp: DateTime<Utc>) -> String {
+    let mut calendar = CalendarWriter::new(Some(name));
+    for series in series {
+        let uid = entry_uid(series.id());
+        let first = (series.datetime_beginning(), series.datetime_ending());
+        calendar.event(series.entry(), first, &uid, stamp, |calendar| {
+            calendar.property("RRULE", series.rrule());
+            for exdate in series.exdates() {
+                calendar.property("EXDATE;TZID=Europe/Warsaw", &local_time(*exdate));
+            }
+        });
+        for entry in series.overrides() {
+            calendar.event(entry, span(entry), &uid, stamp, |calendar| {
+                calendar.property(
+                    "RECURRENCE-ID;TZID=Europe/Warsaw",
+                    &local_time(entry.get_datetime_beginning()),
+                );
+            });
+        }
+    }
+    calendar.finish()
+}
+
+/// RFC 4791 calendar object resource of a single entry, which unlike a feed has no `METHOD`
+pub fn event_calendar(entry: &TimeTableEntry, stamp: DateTime<Utc>) -> String {
+    let mut calendar = CalendarWriter::new(None);
+    let uid = entry_uid(entry.get_id());
+    calendar.event(entry, span(entry), &uid, stamp, |_| {});
+    calendar.finish()
+}
+
+/// Entity tag of the calendar object resource of an entry, changes with its content but not with `DTSTAMP`
+pub fn event_etag(entry: &TimeTableEntry) -> String {
+    hash_id(&["etag", &event_calendar(entry, DateTime::<Utc>::from(UNIX_EPOCH))])
+}
+
+/// CalendarServer `getctag` of a collection, changes whenever any of its entries does
+pub fn calendar_ctag(entries: &[TimeTableEntry]) -> String {
+    etags_ctag(entries.iter().map(event_etag).collect())
+}
+
+/// `calendar_ctag` out of the `event_etag`s of the entries, in any order
+pub fn etags_ctag(mut etags: Vec<String>) -> String {
+    etags.sort_unstable();
+    let mut parts = vec!["ctag"];
+    parts.extend(etags.iter().map(String::as_str));
+    hash_id(&parts)
+}
+
+fn entry_uid(id: &str) -> String {
+    format!("{id}@{UID_DOMAIN}")
+}
+
+/// Beginning and ending of an entry
+fn span(entry: &TimeTableEntry) -> (DateTime<Utc>, DateTime<Utc>) {
+    (entry.get_datetime_beginning(), entry.get_datetime_ending())
+}
+
+/// Writes content lines, folded at 75 octets and ended with CRLF
+struct CalendarWriter {
+    output: String,
+}
+
+impl CalendarWriter {
+    /// Feeds have a `name`, calendar object resources don't
+    fn new(name: Option<&str>) -> Self {
+        let mut calendar = Self {
+            output: String::new(),
+        };
+        calendar.line("BEGIN:VCALENDAR");
+        calendar.line("VERSION:2.0");
+        calendar.line("PRODID:-//pjatk21//Sigma API//PL");
+        calendar.line("CALSCALE:GREGORIAN");
+        if let Some(name) = name {
+            calendar.line("METHOD:PUBLISH");
+            calendar.property("X-WR-CALNAME", &escape(name));
+            calendar.line("X-WR-TIMEZONE:Europe/Warsaw");
+        }
+        for line in VTIMEZONE {
+            calendar.line(line);
+        }
+        calendar
+    }
+
+    /// Writes a `VEVENT` of the entry taking place at `span`, `extra` adds properties before it is closed
+    fn event(
+        &mut self,
+        entry: &TimeTableEntry,
+        (beginning, ending): (DateTime<Utc>, DateTime<Utc>),
+        uid: &str,
+        stamp: DateTime<Utc>,
+        extra: impl FnOnce(&mut Self),
+    ) {
+        self.line("BEGIN:VEVENT");
+        self.property("UID", uid);
+        self.property("DTSTAMP", &stamp.format("%Y%m%dT%H%M%SZ").to_string());
+        self.property("DTSTART;TZID=Europe/Warsaw", &local_time(beginning));
+        self.property("DTEND;TZID=Europe/Warsaw", &local_time(ending));
+        self.property("SUMMARY", &escape(&summary(entry)));
+        self.property(
+            "LOCATION",
+            &escape(&format!("{} {}", entry.get_building(), entry.get_room())),
+        );
+        self.property("DESCRIPTION", &escape(&description(entry)));
+        self.property(
+            "STATUS",
+            if entry.get_status().cancelled() {
+                "CANCELLED"
+            } else {
+                "CONFIRMED"
+            },
+        );
+        extra(self);
+        self.line("END:VEVENT");
+    }
+
+    fn finish(mut self) -> String {
+        self.line("END:VCALENDAR");
+        self.output
+    }
+
+    /// Writes `name:value`, the value has to be escaped already
+    fn property(&mut self, name: &str, value: &str) {
+        self.line(&format!("{name}:{value}"));
+    }
+
+    fn line(&mut self, line: &str) {
+        let mut octets = 0;
+        for c in line.chars() {
+            if octets + c.len_utf8() > 75 {
+                self.output.push_str("\r\n ");
+                octets = 1;
+            }
+            self.output.push(c);
+            octets += c.len_utf8();
+        }
+        self.output.push_str("\r\n");
+    }
+}
+
+/// Subjects and type for classes, title for reservations
+fn summary(entry: &TimeTableEntry) -> String {
+    match entry.get_title() {
+        Some(title) if entry.get_kind().is_reservation() => title.to_string(),
+        _ => format!(
+            "{} ({})",
+            entry.get_subjects().join(", "),
+            entry.get_type_of()
+        ),
+    }
+}
+
+fn description(entry: &TimeTableEntry) -> String {
+    let mut lines = vec![];
+    if !entry.get_subject_codes().is_empty() {
+        lines.push(format!("Kody: {}", entry.get_subject_codes().join(", ")));
+    }
+    if !entry.get_persons().is_empty() {
+        lines.push(format!("Prowadzący: {}", entry.get_persons().join(", ")));
+    }
+    if let Some(groups) = entry.get_groups() {
+        lines.push(format!("Grupy: {}", groups.join(", ")));
+    }
+    if let Some(details) = entry.get_details() {
+        lines.push(details.to_string());
+    }
+    lines.join("\n")
+}
+
+/// Escapes a TEXT value
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn local_time(datetime: DateTime<Utc>) -> String {
+    datetime
+        .with_timezone(&Warsaw)
+        .format("%Y%m%dT%H%M%S")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::{fixtures::mock_entries, series::detect_series};
+
+    #[test]
+    fn series_starts_with_overridden_first_occurrence() {
+        let first_beginning = Utc.with_ymd_and_hms(2024, 11, 6, 7, 30, 0).unwrap();
+        let mut entries = mock_entries(first_beginning, 3);
+        entries[0].room = "A/152".to_string();
+        let series = detect_series(entries);
+        assert_eq!(series[0].overrides().len(), 1);
+
+        let calendar = series_calendar("WIs I.2 - 46c", &series, first_beginning);
+        let events: Vec<&str> = calendar.split("BEGIN:VEVENT").skip(1).collect();
+        assert_eq!(events.len(), 2);
+        assert!(events[0].contains("DTSTART;TZID=Europe/Warsaw:20241106T083000\r\n"));
+        assert!(events[0].contains("DTEND;TZID=Europe/Warsaw:20241106T103000\r\n"));
+        assert!(events[0].contains("LOCATION:B2020 B/227\r\n"));
+        assert!(events[1].contains("RECURRENCE-ID;TZID=Europe/Warsaw:20241106T083000\r\n"));
+        assert!(events[1].contains("LOCATION:B2020 A/152\r\n"));
+    }
+
+    #[test]
+    fn ctag_ignores_order_of_etags() {
+        let entries = mock_entries(Utc.with_ymd_and_hms(2024, 11, 6, 7, 30, 0).unwrap(), 3);
+        let etags: Vec<String> = entries.iter().rev().map(event_etag).collect();
+
+        assert_eq!(etags_ctag(etags), calendar_ctag(&entries));
+        assert_ne!(calendar_ctag(&entries[1..]), calendar_ctag(&entries));
+    }
+}
@@ -0,0 +1,250 @@
+#![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
+
+use std::borrow::Cow;
+
+use bson::{Bson, Regex};
+use poem_openapi::{
+    registry::{MetaSchema, MetaSchemaRef},
+    types::{ParseError, ParseFromJSON, ParseFromParameter, ParseResult, ToJSON, Type},
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+/// Kind of entry, parsed from its Polish label
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum EntryKind {
+    /// Wykład
+    Lecture,
+    /// Ćwiczenia
+    Exercises,
+    /// Laboratorium
+    Laboratory,
+    /// Lektorat
+    LanguageCourse,
+    /// Seminarium
+    Seminar,
+    /// Projekt
+    Project,
+    /// Egzamin
+    Exam,
+    /// Konsultacje
+    Consultation,
+    /// Zebranie, spotkanie
+    Meeting,
+    /// Any other room reservation
+    Reservation,
+    /// Class of a kind not known yet, holds its label.
+    ///
+    /// Shown as `unknown` by the API, stored as `unknown:<label>` so the label survives a round trip.
+    Unknown(String),
+}
+
+impl EntryKind {
+    /// Parses a label, `is_reservation` tells if it came from `#ctl06_TypRezerwacjiLabel`
+    pub fn from_label(label: &str, is_reservation: bool) -> Self {
+        let label = label.trim();
+        let lowercase_label = label.to_lowercase();
+        let known = [
+            ("wykład", EntryKind::Lecture),
+            ("ćwiczenia", EntryKind::Exercises),
+            ("lab", EntryKind::Laboratory),
+            ("lektorat", EntryKind::LanguageCourse),
+            ("seminarium", EntryKind::Seminar),
+            ("projekt", EntryKind::Project),
+            ("egzamin", EntryKind::Exam),
+            ("konsultacje", EntryKind::Consultation),
+            ("zebranie", EntryKind::Meeting),
+            ("spotkanie", EntryKind::Meeting),
+        ];
+        match known
+            .into_iter()
+            .find(|(prefix, _)| lowercase_label.starts_with(prefix))
+        {
+            Some((_, kind)) => kind,
+            None if is_reservation => EntryKind::Reservation,
+            None => EntryKind::Unknown(label.to_string()),
+        }
+    }
+    /// Stable English name of the kind
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EntryKind::Lecture => "lecture",
+            EntryKind::Exercises => "exercises",
+            EntryKind::Laboratory => "laboratory",
+            EntryKind::LanguageCourse => "language_course",
+            EntryKind::Seminar => "seminar",
+            EntryKind::Project => "project",
+            EntryKind::Exam => "exam",
+            EntryKind::Consultation => "consultation",
+            EntryKind::Meeting => "meeting",
+            EntryKind::Reservation => "reservation",
+            EntryKind::Unknown(_) => "unknown",
+        }
+    }
+    /// Whether the entry is a room reservation rather than a class
+    pub fn is_reservation(&self) -> bool {
+        matches!(
+            self,
+            EntryKind::Exam | EntryKind::Consultation | EntryKind::Meeting | EntryKind::Reservation
+        )
+    }
+    /// Parses a stable English name, `None` for names of no kind
+    fn from_name(name: &str) -> Option<Self> {
+        let kind = match name {
+            "lecture" => EntryKind::Lecture,
+            "exercises" => EntryKind::Exercises,
+            "laboratory" => EntryKind::Laboratory,
+            "language_course" => EntryKind::LanguageCourse,
+            "seminar" => EntryKind::Seminar,
+            "project" => EntryKind::Project,
+            "exam" => EntryKind::Exam,
+            "consultation" => EntryKind::Consultation,
+            "meeting" => EntryKind::Meeting,
+            "reservation" => EntryKind::Reservation,
+            "unknown" => EntryKind::Unknown(String::new()),
+            _ => return None,
+        };
+        Some(kind)
+    }
+    /// Stored form, the name with the label of an unknown kind
+    fn to_stored(&self) -> Cow<'static, str> {
+        match self {
+            EntryKind::Unknown(label) if !label.is_empty() => {
+                format!("{UNKNOWN_PREFIX}{label}").into()
+            }
+            kind => kind.as_str().into(),
+        }
+    }
+    /// Parses a stored kind, names stored before labels were kept become unknown kinds as well
+    fn from_stored(stored: &str) -> Self {
+        match stored.strip_prefix(UNKNOWN_PREFIX) {
+            Some(label) => EntryKind::Unknown(label.to_string()),
+            None => EntryKind::from_name(stored)
+                .unwrap_or_else(|| EntryKind::Unknown(stored.to_string())),
+        }
+    }
+    /// MongoDB value matching this kind in the `kind` field, unknown kinds match whatever their label
+    pub fn to_query_value(&self) -> Bson {
+        match self {
+            EntryKind::Unknown(_) => Bson::RegularExpression(Regex {
+                pattern: "^unknown(:|$)".to_string(),
+                options: String::new(),
+            }),
+            kind => Bson::String(kind.as_str().to_string()),
+        }
+    }
+}
+
+/// Prefix of a stored unknown kind followed by its label
+const UNKNOWN_PREFIX: &str = "unknown:";
+
+impl Default for EntryKind {
+    fn default() -> Self {
+        EntryKind::Unknown(String::new())
+    }
+}
+
+impl Serialize for EntryKind {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_stored())
+    }
+}
+
+impl<'de> Deserialize<'de> for EntryKind {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(EntryKind::from_stored(&String::deserialize(deserializer)?))
+    }
+}
+
+impl Type for EntryKind {
+    const IS_REQUIRED: bool = true;
+
+    type RawValueType = Self;
+
+    type RawElementValueType = Self;
+
+    fn name() -> Cow<'static, str> {
+        "EntryKind".into()
+    }
+
+    fn schema_ref() -> MetaSchemaRef {
+        MetaSchemaRef::Inline(Box::new(MetaSchema {
+            description: Some(
+                "Kind of entry: `lecture`, `exercises`, `laboratory`, `language_course`, \
+                `seminar`, `project`, `exam`, `consultation`, `meeting`, `reservation` or `unknown`",
+            ),
+            ..MetaSchema::new("string")
+        }))
+    }
+
+    fn as_raw_value(&self) -> Option<&Self::RawValueType> {
+        Some(self)
+    }
+
+    fn raw_element_iter<'a>(
+        &'a self,
+    ) -> Box<dyn Iterator<Item = &'a Self::RawElementValueType> + 'a> {
+        Box::new(self.as_raw_value().into_iter())
+    }
+}
+
+impl ParseFromJSON for EntryKind {
+    fn parse_from_json(value: Option<Value>) -> ParseResult<Self> {
+        let value = value.unwrap_or_default();
+        if let Value::String(name) = &value {
+            EntryKind::from_name(name).ok_or_else(|| unknown_name(name))
+        } else {
+            Err(ParseError::expected_type(value))
+        }
+    }
+}
+
+impl ParseFromParameter for EntryKind {
+    fn parse_from_parameter(value: &str) -> ParseResult<Self> {
+        EntryKind::from_name(value).ok_or_else(|| unknown_name(value))
+    }
+}
+
+fn unknown_name(name: &str) -> ParseError<EntryKind> {
+    ParseError::custom(format!(
+        "`{name}` is not a kind of entry, e.g. `lecture` or `reservation`"
+    ))
+}
+
+impl ToJSON for EntryKind {
+    fn to_json(&self) -> Option<Value> {
+        Some(Value::String(self.as_str().to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_unknown_names() {
+        assert_eq!(
+            EntryKind::parse_from_parameter("lecture").ok(),
+            Some(EntryKind::Lecture)
+        );
+        assert_eq!(
+            EntryKind::parse_from_parameter("unknown").ok(),
+            Some(EntryKind::Unknown(String::new()))
+        );
+        assert!(EntryKind::parse_from_parameter("lectur").is_err());
+        assert!(EntryKind::parse_from_json(Some(Value::String("lectur".to_string()))).is_err());
+    }
+
+    #[test]
+    fn keeps_label_of_unknown_kind() {
+        let kind = EntryKind::from_label("Warsztaty", false);
+        let stored = bson::to_bson(&kind).unwrap();
+        assert_eq!(stored, Bson::String("unknown:Warsztaty".to_string()));
+        assert_eq!(bson::from_bson::<EntryKind>(stored).unwrap(), kind);
+        assert_eq!(kind.to_json(), Some(Value::String("unknown".to_string())));
+        assert_eq!(
+            bson::from_bson::<EntryKind>(Bson::String("unknown".to_string())).unwrap(),
+            EntryKind::Unknown(String::new())
+        );
+    }
+}
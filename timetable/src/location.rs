@@ -0,0 +1,173 @@
+#![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
+
+use poem_openapi::{Enum, Object};
+use serde::{Deserialize, Serialize};
+
+/// Place which is not a physical room
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Enum)]
+#[serde(rename_all = "snake_case")]
+#[oai(rename_all = "snake_case")]
+pub enum VirtualLocation {
+    /// Microsoft Teams
+    Teams,
+    /// Any other remote class
+    Online,
+}
+
+impl VirtualLocation {
+    pub(crate) fn detect(text: &str) -> Option<Self> {
+        let text = text.to_lowercase();
+        if text.contains("teams") {
+            Some(VirtualLocation::Teams)
+        } else if ["online", "internet", "zdaln", "e-learning", "wirtualn"]
+            .iter()
+            .any(|marker| text.contains(marker))
+        {
+            Some(VirtualLocation::Online)
+        } else {
+            None
+        }
+    }
+}
+
+/// Building and room split into their parts, e.g. `B2020` and `B/227`
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, Hash, Object)]
+pub struct Location {
+    /// Building
+    pub(crate) building: String,
+    /// Wing, e.g. `B` of `B/227`
+    pub(crate) wing: Option<String>,
+    /// Floor, e.g. `2` of `B/227`
+    pub(crate) floor: Option<i32>,
+    /// Number of the room, e.g. `227` of `B/227`
+    pub(crate) room_number: Option<String>,
+    /// Set when the entry doesn't take place in a physical room
+    pub(crate) virtual_location: Option<VirtualLocation>,
+}
+
+impl Location {
+    pub fn parse(building: &str, room: &str) -> Self {
+        let building = building.trim();
+        let room = room.trim();
+        let virtual_location =
+            VirtualLocation::detect(room).or_else(|| VirtualLocation::detect(building));
+        let (wing, room_number) = match room.split_once('/') {
+            Some((wing, room_number)) if virtual_location.is_none() => (
+                Some(wing.trim().to_uppercase()).filter(|wing| !wing.is_empty()),
+                Some(room_number.trim().to_string()).filter(|number| !number.is_empty()),
+            ),
+            _ => (None, None),
+        };
+        let floor = room_number.as_deref().and_then(parse_floor);
+        Self {
+            building: building.to_string(),
+            wing,
+            floor,
+            room_number,
+            virtual_location,
+        }
+    }
+    pub fn building(&self) -> &str {
+        &self.building
+    }
+    pub fn wing(&self) -> Option<&str> {
+        self.wing.as_deref()
+    }
+    pub fn floor(&self) -> Option<i32> {
+        self.floor
+    }
+    pub fn room_number(&self) -> Option<&str> {
+        self.room_number.as_deref()
+    }
+    pub fn virtual_location(&self) -> Option<VirtualLocation> {
+        self.virtual_location
+    }
+    pub fn is_remote(&self) -> bool {
+        self.virtual_location.is_some()
+    }
+}
+
+/// Floor is the hundreds of a room number, `227` is on the second floor, `15` on the ground floor
+/// and basement rooms like `-105` below it
+fn parse_floor(room_number: &str) -> Option<i32> {
+    let (sign, number) = match room_number.strip_prefix('-') {
+        Some(number) => (-1, number),
+        None => (1, room_number),
+    };
+    let digits: String = number.chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits
+        .parse::<i32>()
+        .ok()
+        .map(|number| sign * (number / 100))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_room_into_wing_floor_and_number() {
+        let location = Location::parse(" B2020 ", "b/227");
+        assert_eq!(location.building(), "B2020");
+        assert_eq!(location.wing(), Some("B"));
+        assert_eq!(location.floor(), Some(2));
+        assert_eq!(location.room_number(), Some("227"));
+        assert!(!location.is_remote());
+    }
+
+    #[test]
+    fn numbers_below_hundred_are_on_ground_floor() {
+        assert_eq!(Location::parse("A2020", "A/15").floor(), Some(0));
+        assert_eq!(Location::parse("A2020", "A/015").floor(), Some(0));
+    }
+
+    #[test]
+    fn basement_rooms_are_below_ground_floor() {
+        let location = Location::parse("A2020", "A/-105");
+        assert_eq!(location.room_number(), Some("-105"));
+        assert_eq!(location.floor(), Some(-1));
+        assert_eq!(parse_floor("-15"), Some(0));
+    }
+
+    #[test]
+    fn letter_suffix_is_kept_in_number() {
+        let location = Location::parse("B2020", "B/227a");
+        assert_eq!(location.floor(), Some(2));
+        assert_eq!(location.room_number(), Some("227a"));
+    }
+
+    #[test]
+    fn rooms_without_number_have_no_floor() {
+        let location = Location::parse("B2020", "Aula");
+        assert_eq!(location.wing(), None);
+        assert_eq!(location.floor(), None);
+        assert_eq!(location.room_number(), None);
+
+        let location = Location::parse("B2020", "B/Aula");
+        assert_eq!(location.wing(), Some("B"));
+        assert_eq!(location.floor(), None);
+        assert_eq!(location.room_number(), Some("Aula"));
+    }
+
+    #[test]
+    fn detects_virtual_locations() {
+        let teams = Location::parse("Online", "MS Teams");
+        assert_eq!(teams.virtual_location(), Some(VirtualLocation::Teams));
+        assert_eq!(teams.floor(), None);
+
+        for (building, room) in [
+            ("B2020", "Online"),
+            ("Zdalnie", "A/1"),
+            ("B2020", "e-learning"),
+        ] {
+            let location = Location::parse(building, room);
+            assert_eq!(
+                location.virtual_location(),
+                Some(VirtualLocation::Online),
+                "{room}"
+            );
+            assert_eq!(location.room_number(), None, "{room}");
+            assert!(location.is_remote());
+        }
+    }
+}
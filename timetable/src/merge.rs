@@ -0,0 +1,203 @@
+#![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
+
+use std::cmp::Ordering;
+
+use chrono::{DateTime, Duration, Utc};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+
+use crate::timetable::{hash_id, TimeTableEntry};
+
+/// One of the entries a merged entry was made of
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Object)]
+pub struct EntryPart {
+    /// Id of the original entry
+    pub(crate) id: String,
+    /// Date and time of beginning of the original entry
+    pub(crate) datetime_beginning: DateTime<Utc>,
+    /// Date and time of ending of the original entry
+    pub(crate) datetime_ending: DateTime<Utc>,
+}
+
+impl EntryPart {
+    fn of(entry: &TimeTableEntry) -> Self {
+        Self {
+            id: entry.id.clone(),
+            datetime_beginning: entry.datetime_beginning,
+            datetime_ending: entry.datetime_ending,
+        }
+    }
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    pub fn datetime_beginning(&self) -> DateTime<Utc> {
+        self.datetime_beginning
+    }
+    pub fn datetime_ending(&self) -> DateTime<Utc> {
+        self.datetime_ending
+    }
+}
+
+/// Attributes which have to be identical for two entries to be the same class
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct ClassKey<'a> {
+    title: Option<&'a str>,
+    type_of: &'a str,
+    subjects: Vec<&'a str>,
+    subject_codes: Vec<&'a str>,
+    persons: Vec<&'a str>,
+    groups: Vec<&'a str>,
+    building: &'a str,
+    room: &'a str,
+    status: [bool; 4],
+}
+
+impl<'a> ClassKey<'a> {
+    fn of(entry: &'a TimeTableEntry) -> Self {
+        let sorted = |values: &'a [String]| {
+            let mut values: Vec<&str> = values.iter().map(String::as_str).collect();
+            values.sort_unstable();
+            values
+        };
+        Self {
+            title: entry.title.as_deref(),
+            type_of: &entry.type_of,
+            subjects: sorted(&entry.subjects),
+            subject_codes: sorted(&entry.subject_codes),
+            persons: sorted(&entry.persons),
+            groups: sorted(entry.groups.as_deref().unwrap_or_default()),
+            building: &entry.building,
+            room: &entry.room,
+            status: [
+                entry.status.cancelled,
+                entry.status.moved,
+                entry.status.online_only,
+                entry.status.substitute,
+            ],
+        }
+    }
+}
+
+/// Coalesces entries of the same class that overlap, touch or are at most `max_gap` apart
+/// into a single entry spanning all of them, e.g. a lab split into consecutive cells.
+///
+/// Merged entries list the original ones in `parts` and get an id derived from them, the other
+/// entries are returned as they are. Duplicates of an entry are dropped. The result is sorted by
+/// beginning.
+pub fn merge_entries(entries: Vec<TimeTableEntry>, max_gap: Duration) -> Vec<TimeTableEntry> {
+    let groups = merge_groups(&entries, max_gap);
+    let mut entries: Vec<Option<TimeTableEntry>> = entries.into_iter().map(Some).collect();
+
+    let mut merged: Vec<TimeTableEntry> = vec![];
+    for group in groups {
+        let mut group = group
+            .into_iter()
+            .map(|index| entries[index].take().expect("Every entry is in one group"));
+        let mut last = group.next().expect("Groups aren't empty");
+        for entry in group {
+            if last.id == entry.id || last.parts.iter().any(|part| part.id == entry.id) {
+                continue;
+            }
+            if last.parts.is_empty() {
+                last.parts.push(EntryPart::of(&last));
+            }
+            last.parts.push(EntryPart::of(&entry));
+            last.datetime_ending = last.datetime_ending.max(entry.datetime_ending);
+            last.dst_adjustment = last.dst_adjustment.or(entry.dst_adjustment);
+        }
+        merged.push(last);
+    }
+
+    for entry in merged.iter_mut().filter(|entry| !entry.parts.is_empty()) {
+        let mut ids: Vec<&str> = vec!["merged"];
+        ids.extend(entry.parts.iter().map(|part| part.id.as_str()));
+        entry.id = hash_id(&ids);
+    }
+    merged.sort_by_key(|entry| entry.datetime_beginning);
+    merged
+}
+
+/// Indices of the entries `merge_entries` coalesces into one, in the order they are merged.
+///
+/// Every entry is in exactly one group, entries that aren't merged with any other make up a group
+/// of their own.
+pub fn merge_groups(entries: &[TimeTableEntry], max_gap: Duration) -> Vec<Vec<usize>> {
+    let mut order: Vec<usize> = (0..entries.len()).collect();
+    order.sort_by(|a, b| compare_for_merge(&entries[*a], &entries[*b]));
+
+    // Each group along with its ending so far
+    let mut groups: Vec<(Vec<usize>, DateTime<Utc>)> = vec![];
+    for index in order {
+        let entry = &entries[index];
+        match groups.last_mut() {
+            Some((group, ending))
+                if ClassKey::of(&entries[group[0]]) == ClassKey::of(entry)
+                    && entry.datetime_beginning <= *ending + max_gap =>
+            {
+                group.push(index);
+                *ending = (*ending).max(entry.datetime_ending);
+            }
+            _ => groups.push((vec![index], entry.datetime_ending)),
+        }
+    }
+    groups.into_iter().map(|(group, _)| group).collect()
+}
+
+fn compare_for_merge(a: &TimeTableEntry, b: &TimeTableEntry) -> Ordering {
+    ClassKey::of(a)
+        .cmp(&ClassKey::of(b))
+        .then(a.datetime_beginning.cmp(&b.datetime_beginning))
+        .then(a.datetime_ending.cmp(&b.datetime_ending))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::fixtures::mock_entry_at;
+
+    fn beginning() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 11, 6, 7, 30, 0).unwrap()
+    }
+
+    #[test]
+    fn merges_consecutive_cells_of_a_class() {
+        let first = mock_entry_at(beginning());
+        let second = mock_entry_at(beginning() + Duration::hours(2));
+        let merged = merge_entries(vec![second.clone(), first.clone()], Duration::zero());
+        assert_eq!(merged.len(), 1);
+        assert_eq!(
+            merged[0].get_datetime_beginning(),
+            first.get_datetime_beginning()
+        );
+        assert_eq!(
+            merged[0].get_datetime_ending(),
+            second.get_datetime_ending()
+        );
+        let parts: Vec<&str> = merged[0].get_parts().iter().map(EntryPart::id).collect();
+        assert_eq!(parts, [first.get_id(), second.get_id()]);
+        assert_ne!(merged[0].get_id(), first.get_id());
+    }
+
+    #[test]
+    fn keeps_entries_apart_beyond_the_gap() {
+        let first = mock_entry_at(beginning());
+        let second = mock_entry_at(beginning() + Duration::hours(3));
+        assert_eq!(
+            merge_entries(vec![first.clone(), second.clone()], Duration::minutes(30)).len(),
+            2
+        );
+        assert_eq!(
+            merge_entries(vec![first, second], Duration::hours(1)).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn drops_duplicates() {
+        let entry = mock_entry_at(beginning());
+        let merged = merge_entries(vec![entry.clone(), entry.clone()], Duration::zero());
+        assert_eq!(merged, [entry]);
+    }
+}
@@ -0,0 +1,352 @@
+#![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
+
+use std::{error::Error, fmt::Display};
+
+use bson::{doc, Bson, Document};
+use futures::TryStreamExt;
+use mongodb::{options::ReplaceOptions, Collection, IndexModel};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+
+use crate::{status::EntryStatus, timetable::DstAdjustment, timetable::TimeTableEntry};
+
+/// Version of the stored document layout, bump it together with a new step in `migrate_document`.
+///
+/// Documents written before versioning have no `schema_version` and count as version `0`.
+pub const SCHEMA_VERSION: i32 = 2;
+
+/// Reason why a stored document could not be upgraded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationError {
+    /// Document is missing a field every version has, or it has the wrong type
+    Malformed { id: String, field: &'static str },
+    /// Document was written by a newer version than this one
+    NewerVersion { id: String, version: i32 },
+}
+
+impl Display for MigrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MigrationError::Malformed { id, field } => {
+                write!(f, "Migration error: document {id} has no valid {field}")
+            }
+            MigrationError::NewerVersion { id, version } => {
+                write!(
+                    f,
+                    "Migration error: document {id} has schema version {version}, newer than {SCHEMA_VERSION}"
+                )
+            }
+        }
+    }
+}
+
+impl Error for MigrationError {}
+
+/// Outcome of `migrate_collection`
+#[derive(Debug, Serialize, Deserialize, Clone, Default, Object)]
+pub struct MigrationReport {
+    /// Documents upgraded in place
+    pub migrated: u32,
+    /// Documents upgraded under a new `_id`, the old document was removed
+    pub rekeyed: u32,
+    /// Documents that could not be upgraded, with the reason
+    pub failed: Vec<String>,
+}
+
+/// Schema version of a stored document, `0` when it has none
+pub fn schema_version(document: &Document) -> i32 {
+    match document.get("schema_version") {
+        Some(Bson::Int32(version)) => *version,
+        Some(Bson::Int64(version)) => *version as i32,
+        _ => 0,
+    }
+}
+
+/// Matches documents of any schema version other than the current one
+pub fn outdated_filter() -> Document {
+    doc! {"schema_version": {"$ne": SCHEMA_VERSION}}
+}
+
+/// Upgrades a stored document to `SCHEMA_VERSION`, one version at a time
+pub fn migrate_document(mut document: Document) -> Result<Document, MigrationError> {
+    loop {
+        document = match schema_version(&document) {
+            SCHEMA_VERSION => return Ok(document),
+            0 => from_unversioned(&document)?,
+            1 => from_v1(document)?,
+            version => {
+                return Err(MigrationError::NewerVersion {
+                    id: stored_id(&document).to_string(),
+                    version,
+                })
+            }
+        };
+    }
+}
+
+/// Upgrades every outdated document of the collection.
+///
+/// Documents whose id changes are inserted under the new id and then removed, so an upgraded
+/// document replaces a copy already stored under its new id instead of duplicating it.
+pub async fn migrate_collection(
+    collection: &Collection<Document>,
+) -> Result<MigrationReport, mongodb::error::Error> {
+    collection
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! {"schema_version": 1})
+                .build(),
+            None,
+        )
+        .await?;
+    let outdated: Vec<Document> = collection
+        .find(outdated_filter(), None)
+        .await?
+        .try_collect()
+        .await?;
+    let mut report = MigrationReport::default();
+    for document in outdated {
+        let old_id = stored_id(&document);
+        let migrated = match migrate_document(document) {
+            Ok(migrated) => migrated,
+            Err(err) => {
+                report.failed.push(err.to_string());
+                continue;
+            }
+        };
+        let new_id = stored_id(&migrated);
+        collection
+            .replace_one(
+                doc! {"_id": new_id.clone()},
+                migrated,
+                ReplaceOptions::builder().upsert(true).build(),
+            )
+            .await?;
+        if new_id == old_id {
+            report.migrated += 1;
+        } else {
+            collection.delete_one(doc! {"_id": old_id}, None).await?;
+            report.rekeyed += 1;
+        }
+    }
+    Ok(report)
+}
+
+fn stored_id(document: &Document) -> Bson {
+    document.get("_id").cloned().unwrap_or(Bson::Null)
+}
+
+/// Version `0`: documents written before versioning, with an `ObjectId` or content `_id`,
+/// `students_count` as a plain string and possibly none of the parsed fields.
+///
+/// Every derived field is computed again from the raw ones, which yields the current version directly.
+fn from_unversioned(document: &Document) -> Result<Document, MigrationError> {
+    let malformed = |field| MigrationError::Malformed {
+        id: stored_id(document).to_string(),
+        field,
+    };
+    let string = |field| document.get_str(field).map_err(|_| malformed(field));
+    let optional_string = |field| document.get_str(field).ok();
+    let strings = |field| -> Result<Vec<String>, MigrationError> {
+        Ok(document
+            .get_array(field)
+            .map_err(|_| malformed(field))?
+            .iter()
+            .filter_map(|value| value.as_str().map(str::to_string))
+            .collect())
+    };
+    let datetime = |field| {
+        document
+            .get_datetime(field)
+            .map(|datetime| datetime.to_chrono())
+            .map_err(|_| malformed(field))
+    };
+
+    let title = optional_string("title");
+    let mut builder = TimeTableEntry::builder()
+        .persons(strings("persons")?)
+        .type_of(string("type_of")?)
+        // Only reservations have a title, the flag only matters for labels of unknown kinds
+        .reservation(title.is_some())
+        .subjects(strings("subjects")?)
+        .subject_codes(strings("subject_codes")?)
+        .building(string("building")?)
+        .room(string("room")?)
+        .datetime_beginning(datetime("datetime_beginning")?)
+        .datetime_ending(datetime("datetime_ending")?);
+    if let Some(title) = title {
+        builder = builder.title(title);
+    }
+    if let Some(details) = optional_string("details") {
+        builder = builder.details(details);
+    }
+    if let Ok(groups) = document.get_array("groups") {
+        builder = builder.groups(
+            groups
+                .iter()
+                .filter_map(|group| group.as_str().map(str::to_string)),
+        );
+    }
+    let students_count = match document.get("students_count") {
+        Some(Bson::String(raw)) => Some(raw.as_str()),
+        Some(Bson::Document(students_count)) => students_count.get_str("raw").ok(),
+        _ => None,
+    };
+    if let Some(students_count) = students_count {
+        builder = builder.students_count(students_count);
+    }
+    if let Some(source_id) = optional_string("source_id") {
+        builder = builder.source_id(source_id);
+    }
+    let mut entry = builder.build_unchecked();
+    entry.dst_adjustment = document
+        .get("dst_adjustment")
+        .cloned()
+        .and_then(|dst_adjustment| bson::from_bson::<Option<DstAdjustment>>(dst_adjustment).ok())
+        .flatten();
+    bson::to_document(&entry).map_err(|_| malformed("_id"))
+}
+
+/// Version `1`: entries without `status`
+fn from_v1(mut document: Document) -> Result<Document, MigrationError> {
+    let status = EntryStatus::detect(
+        document.get_str("title").ok(),
+        document.get_str("details").ok(),
+    );
+    let status = bson::to_bson(&status).map_err(|_| MigrationError::Malformed {
+        id: stored_id(&document).to_string(),
+        field: "status",
+    })?;
+    document.insert("status", status);
+    document.insert("schema_version", 2);
+    Ok(document)
+}
+
+#[cfg(test)]
+mod tests {
+    use bson::oid::ObjectId;
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+
+    use super::*;
+    use crate::fixtures::mock_entry_at;
+
+    fn beginning() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 11, 6, 7, 30, 0).unwrap()
+    }
+
+    /// `mock_entry_at(beginning())` as stored before versioning
+    fn unversioned() -> Document {
+        doc! {
+            "_id": ObjectId::parse_str("63f1c0ffee00000000000abc").unwrap(),
+            "title": "Ostatni wykład",
+            "persons": ["Niezgoda Adam", "Tomaszewski Michał"],
+            "details": "Podsumowanie semestru",
+            "type_of": "Wykład",
+            "subjects": ["Systemy operacyjne", "Programowanie obiektowe i GUI"],
+            "subject_codes": ["SOP", "GUI"],
+            "groups": ["WIs I.2 - 46c", "WIS I.2 - 23c"],
+            "students_count": "115 115 ITN",
+            "building": "B2020",
+            "room": "B/227",
+            "datetime_beginning": bson::DateTime::from_chrono(beginning()),
+            "datetime_ending": bson::DateTime::from_chrono(beginning() + Duration::hours(2)),
+        }
+    }
+
+    /// Current document of a cancelled class, as version `1` stored it without `status`
+    fn v1() -> (Document, TimeTableEntry) {
+        let entry = TimeTableEntry::builder()
+            .persons(["Niezgoda Adam"])
+            .details("Zajęcia odwołane")
+            .type_of("Ćwiczenia")
+            .subjects(["Systemy operacyjne"])
+            .building("B2020")
+            .room("A/152")
+            .datetime_beginning(beginning())
+            .datetime_ending(beginning() + Duration::hours(2))
+            .build()
+            .unwrap();
+        let mut document = bson::to_document(&entry).unwrap();
+        document.remove("status");
+        document.insert("schema_version", 1);
+        (document, entry)
+    }
+
+    #[test]
+    fn unversioned_document_gets_id_from_content() {
+        let migrated = migrate_document(unversioned()).unwrap();
+        let expected = mock_entry_at(beginning());
+        assert_eq!(migrated.get_str("_id"), Ok(expected.get_id()));
+        assert_eq!(schema_version(&migrated), SCHEMA_VERSION);
+        let entry: TimeTableEntry = bson::from_document(migrated).unwrap();
+        assert_eq!(entry, expected);
+    }
+
+    #[test]
+    fn unversioned_students_count_is_parsed() {
+        let migrated = migrate_document(unversioned()).unwrap();
+        let students_count = migrated.get_document("students_count").unwrap();
+        assert_eq!(students_count.get_i64("enrolled"), Ok(115));
+        assert_eq!(students_count.get_i64("limit"), Ok(115));
+        assert_eq!(students_count.get_str("program"), Ok("ITN"));
+        assert_eq!(students_count.get_str("raw"), Ok("115 115 ITN"));
+    }
+
+    #[test]
+    fn v1_document_gets_status() {
+        let (document, entry) = v1();
+        let migrated = migrate_document(document).unwrap();
+        assert_eq!(schema_version(&migrated), SCHEMA_VERSION);
+        assert_eq!(
+            migrated
+                .get_document("status")
+                .unwrap()
+                .get_bool("cancelled"),
+            Ok(true)
+        );
+        assert_eq!(
+            bson::from_document::<TimeTableEntry>(migrated).unwrap(),
+            entry
+        );
+    }
+
+    #[test]
+    fn current_document_is_left_alone() {
+        let (_, entry) = v1();
+        let document = bson::to_document(&entry).unwrap();
+        assert_eq!(migrate_document(document.clone()), Ok(document));
+    }
+
+    #[test]
+    fn missing_field_is_reported() {
+        let mut document = unversioned();
+        document.remove("room");
+        let error = migrate_document(document).unwrap_err();
+        assert_eq!(
+            error,
+            MigrationError::Malformed {
+                id: Bson::ObjectId(ObjectId::parse_str("63f1c0ffee00000000000abc").unwrap())
+                    .to_string(),
+                field: "room",
+            }
+        );
+        assert!(error.to_string().ends_with("has no valid room"));
+    }
+
+    #[test]
+    fn newer_version_is_reported() {
+        let (mut document, entry) = v1();
+        document.insert("schema_version", SCHEMA_VERSION + 1);
+        let error = migrate_document(document).unwrap_err();
+        assert_eq!(
+            error,
+            MigrationError::NewerVersion {
+                id: Bson::String(entry.get_id().to_string()).to_string(),
+                version: SCHEMA_VERSION + 1,
+            }
+        );
+        assert!(error
+            .to_string()
+            .ends_with(&format!("newer than {SCHEMA_VERSION}")));
+    }
+}
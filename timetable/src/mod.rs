@@ -1,2 +1,20 @@
 pub mod timetable;
-pub mod altapi_timetable;
\ No newline at end of file
+pub mod altapi_timetable;
+pub mod builder;
+pub mod day_page;
+pub mod error;
+#[cfg(any(test, feature = "test-util"))]
+pub mod fixtures;
+pub mod filter;
+pub mod group;
+pub mod ics;
+pub mod kind;
+pub mod location;
+pub mod merge;
+pub mod migration;
+pub mod person;
+pub mod series;
+pub mod source;
+pub mod status;
+pub mod students;
+mod text;
\ No newline at end of file
@@ -0,0 +1,265 @@
+#![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
+
+use std::collections::BTreeMap;
+
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+
+use crate::text::fold;
+
+/// Academic titles in the form they are displayed, keyed by their lowercase form without dots
+const TITLES: [(&str, &str); 10] = [
+    ("dr", "dr"),
+    ("hab", "hab."),
+    ("inż", "inż."),
+    ("inz", "inż."),
+    ("mgr", "mgr"),
+    ("prof", "prof."),
+    ("lic", "lic."),
+    ("doc", "doc."),
+    ("mba", "MBA"),
+    ("phd", "PhD"),
+];
+
+/// Tutor or person who made a reservation, e.g. `dr inż. Niezgoda Adam`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Object)]
+pub struct Person {
+    /// Stable id, the same for every ordering of names and with or without titles
+    pub(crate) id: String,
+    /// Surname
+    pub(crate) surname: String,
+    /// Given names
+    pub(crate) given_names: Vec<String>,
+    /// Academic titles
+    pub(crate) titles: Vec<String>,
+}
+
+impl Person {
+    /// Parses a person, assuming the `Surname Name` ordering used by the plan
+    pub fn parse(raw: &str) -> Option<Self> {
+        let mut titles = vec![];
+        let mut names = vec![];
+        for token in raw.split_whitespace() {
+            match title(token) {
+                Some(title) => titles.push(title.to_string()),
+                None => names.push(token.to_string()),
+            }
+        }
+        if names.is_empty() {
+            return None;
+        }
+        let surname = names.remove(0);
+        let mut person = Self {
+            id: String::new(),
+            surname,
+            given_names: names,
+            titles,
+        };
+        person.id = person_id(&person.names());
+        Some(person)
+    }
+    /// Returns the id a raw name would get, without building the whole person
+    pub fn id_of(raw: &str) -> String {
+        person_id(
+            &raw.split_whitespace()
+                .filter(|token| title(token).is_none())
+                .collect::<Vec<_>>(),
+        )
+    }
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    pub fn surname(&self) -> &str {
+        &self.surname
+    }
+    pub fn given_names(&self) -> &[String] {
+        &self.given_names
+    }
+    pub fn titles(&self) -> &[String] {
+        &self.titles
+    }
+    fn names(&self) -> Vec<&str> {
+        std::iter::once(self.surname.as_str())
+            .chain(self.given_names.iter().map(String::as_str))
+            .collect()
+    }
+}
+
+/// Canonical person together with every spelling found in the plan
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Object)]
+pub struct Tutor {
+    /// Canonical person
+    pub(crate) person: Person,
+    /// Every spelling of the person
+    pub(crate) aliases: Vec<String>,
+}
+
+impl Tutor {
+    pub fn person(&self) -> &Person {
+        &self.person
+    }
+    pub fn aliases(&self) -> &[String] {
+        &self.aliases
+    }
+}
+
+/// Table mapping every spelling of a person onto one canonical person
+#[derive(Debug, Default, Clone)]
+pub struct PersonAliases {
+    people: BTreeMap<String, BTreeMap<String, usize>>,
+}
+
+impl PersonAliases {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Records `count` occurrences of a spelling
+    pub fn insert(&mut self, raw: &str, count: usize) {
+        let raw = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+        if let Some(person) = Person::parse(&raw) {
+            *self
+                .people
+                .entry(person.id)
+                .or_default()
+                .entry(raw)
+                .or_default() += count;
+        }
+    }
+    /// Canonical person for any spelling recorded in the table
+    pub fn canonical(&self, raw: &str) -> Option<Tutor> {
+        let id = Person::id_of(raw);
+        self.people.get(&id).and_then(canonical_tutor)
+    }
+    /// Canonical persons, sorted by id
+    pub fn tutors(&self) -> Vec<Tutor> {
+        self.people.values().filter_map(canonical_tutor).collect()
+    }
+}
+
+/// The most frequent ordering of names wins, titles are taken from the most complete spelling
+fn canonical_tutor(variants: &BTreeMap<String, usize>) -> Option<Tutor> {
+    let (most_frequent, _) = variants
+        .iter()
+        .max_by(|(raw_a, count_a), (raw_b, count_b)| count_a.cmp(count_b).then(raw_b.cmp(raw_a)))?;
+    let mut person = Person::parse(most_frequent)?;
+    person.titles = variants
+        .keys()
+        .filter_map(|raw| Person::parse(raw))
+        .map(|person| person.titles)
+        .max_by_key(|titles| titles.len())
+        .unwrap_or_default();
+    Some(Tutor {
+        person,
+        aliases: variants.keys().cloned().collect(),
+    })
+}
+
+fn title(token: &str) -> Option<&'static str> {
+    let key = token.trim_end_matches('.').to_lowercase();
+    TITLES
+        .iter()
+        .find(|(title_key, _)| *title_key == key)
+        .map(|(_, title)| *title)
+}
+
+fn person_id(names: &[&str]) -> String {
+    let mut names: Vec<String> = names.iter().map(|name| slug(name)).collect();
+    names.retain(|name| !name.is_empty());
+    names.sort();
+    names.join("-")
+}
+
+fn slug(name: &str) -> String {
+    fold(name)
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timetable::TimeTableEntry;
+
+    #[test]
+    fn parses_titles_in_any_form() {
+        let person = Person::parse("dr hab. INŻ Niezgoda Adam").unwrap();
+        assert_eq!(person.titles(), ["dr", "hab.", "inż."]);
+        assert_eq!(person.surname(), "Niezgoda");
+        assert_eq!(person.given_names(), ["Adam"]);
+        assert_eq!(person.id(), "adam-niezgoda");
+
+        let person = Person::parse("Nowak Anna Maria MBA").unwrap();
+        assert_eq!(person.titles(), ["MBA"]);
+        assert_eq!(person.given_names(), ["Anna", "Maria"]);
+    }
+
+    #[test]
+    fn titles_alone_are_no_person() {
+        assert_eq!(Person::parse("dr inż."), None);
+        assert_eq!(Person::parse("  "), None);
+    }
+
+    #[test]
+    fn id_ignores_order_titles_and_diacritics() {
+        let id = Person::parse("Tomaszewski Michał")
+            .unwrap()
+            .id()
+            .to_string();
+        assert_eq!(id, "michal-tomaszewski");
+        for raw in [
+            "Michał Tomaszewski",
+            "mgr Tomaszewski Michal",
+            "TOMASZEWSKI MICHAŁ",
+        ] {
+            assert_eq!(Person::parse(raw).unwrap().id(), id, "{raw}");
+            assert_eq!(Person::id_of(raw), id, "{raw}");
+        }
+        assert_eq!(Person::id_of("Kowalska-Nowak Anna"), "anna-kowalska-nowak");
+    }
+
+    #[test]
+    fn every_tutor_of_a_cell_is_parsed() {
+        let entry = TimeTableEntry::builder()
+            .persons(["dr inż. Niezgoda Adam", "Tomaszewski Michał", "mgr"])
+            .room("B/227")
+            .build_unchecked();
+        let ids: Vec<&str> = entry.get_tutors().iter().map(Person::id).collect();
+        assert_eq!(ids, ["adam-niezgoda", "michal-tomaszewski"]);
+    }
+
+    #[test]
+    fn aliases_resolve_to_canonical_person() {
+        let mut aliases = PersonAliases::new();
+        aliases.insert("Niezgoda Adam", 3);
+        aliases.insert("Niezgoda   Adam", 1);
+        aliases.insert("dr inż. Adam Niezgoda", 2);
+        aliases.insert("Tomaszewski Michał", 1);
+        aliases.insert("dr", 5);
+
+        let tutor = aliases.canonical("adam niezgoda").unwrap();
+        assert_eq!(tutor.person().surname(), "Niezgoda");
+        assert_eq!(tutor.person().given_names(), ["Adam"]);
+        assert_eq!(tutor.person().titles(), ["dr", "inż."]);
+        assert_eq!(tutor.aliases(), ["Niezgoda Adam", "dr inż. Adam Niezgoda"]);
+        assert_eq!(aliases.canonical("Kowalski Jan"), None);
+
+        let ids: Vec<String> = aliases
+            .tutors()
+            .iter()
+            .map(|tutor| tutor.person().id().to_string())
+            .collect();
+        assert_eq!(ids, ["adam-niezgoda", "michal-tomaszewski"]);
+    }
+
+    #[test]
+    fn tied_spellings_are_settled_by_order() {
+        let variants = BTreeMap::from([
+            ("Niezgoda Adam".to_string(), 2),
+            ("Adam Niezgoda".to_string(), 2),
+        ]);
+        let tutor = canonical_tutor(&variants).unwrap();
+        assert_eq!(tutor.person().surname(), "Adam");
+        assert_eq!(canonical_tutor(&BTreeMap::new()), None);
+    }
+}
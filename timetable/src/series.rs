@@ -0,0 +1,265 @@
+#![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::{Europe::Warsaw, Tz};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+
+use crate::timetable::{hash_id, TimeTableEntry};
+
+/// Occurrences of a class repeating every week or every few weeks
+#[derive(Debug, Serialize, Deserialize, Clone, Object)]
+pub struct Series {
+    /// Stable id of series, derived from its first occurrence
+    pub(crate) id: String,
+    /// Occurrence the others are the same as, apart from the date
+    pub(crate) entry: TimeTableEntry,
+    /// Date and time of beginning of the first occurrence, `DTSTART` of the rule
+    pub(crate) datetime_beginning: DateTime<Utc>,
+    /// Date and time of ending of the first occurrence
+    pub(crate) datetime_ending: DateTime<Utc>,
+    /// RFC 5545 recurrence rule, e.g. `FREQ=WEEKLY;INTERVAL=2;COUNT=7`, in Europe/Warsaw time
+    pub(crate) rrule: String,
+    /// Occurrences of the rule which don't take place (`EXDATE`)
+    pub(crate) exdates: Vec<DateTime<Utc>>,
+    /// Occurrences which differ from `entry`, e.g. in room or status (`RECURRENCE-ID`)
+    pub(crate) overrides: Vec<TimeTableEntry>,
+}
+
+impl Series {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    pub fn entry(&self) -> &TimeTableEntry {
+        &self.entry
+    }
+    pub fn datetime_beginning(&self) -> DateTime<Utc> {
+        self.datetime_beginning
+    }
+    pub fn datetime_ending(&self) -> DateTime<Utc> {
+        self.datetime_ending
+    }
+    pub fn rrule(&self) -> &str {
+        &self.rrule
+    }
+    pub fn exdates(&self) -> &[DateTime<Utc>] {
+        &self.exdates
+    }
+    pub fn overrides(&self) -> &[TimeTableEntry] {
+        &self.overrides
+    }
+    /// Adds local times to the entry and the overrides, see `TimeTableEntry::with_local_time`
+    pub fn with_local_time(mut self, timezone: Tz) -> Self {
+        self.entry = self.entry.with_local_time(timezone);
+        self.overrides = self
+            .overrides
+            .into_iter()
+            .map(|entry| entry.with_local_time(timezone))
+            .collect();
+        self
+    }
+}
+
+/// Attributes every occurrence of a series shares, times are local so series survive DST changes
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct SeriesKey {
+    title: Option<String>,
+    type_of: String,
+    subjects: Vec<String>,
+    subject_codes: Vec<String>,
+    persons: Vec<String>,
+    groups: Vec<String>,
+    weekday: u32,
+    time_beginning: NaiveTime,
+    time_ending: NaiveTime,
+}
+
+impl SeriesKey {
+    fn of(entry: &TimeTableEntry) -> Self {
+        let sorted = |values: &[String]| {
+            let mut values = values.to_vec();
+            values.sort_unstable();
+            values
+        };
+        let beginning = entry.datetime_beginning.with_timezone(&Warsaw);
+        Self {
+            title: entry.title.clone(),
+            type_of: entry.type_of.clone(),
+            subjects: sorted(&entry.subjects),
+            subject_codes: sorted(&entry.subject_codes),
+            persons: sorted(&entry.persons),
+            groups: sorted(entry.groups.as_deref().unwrap_or_default()),
+            weekday: beginning.weekday().num_days_from_monday(),
+            time_beginning: beginning.time(),
+            time_ending: entry.datetime_ending.with_timezone(&Warsaw).time(),
+        }
+    }
+}
+
+/// Attributes an occurrence may change without leaving its series
+type Variant<'a> = (&'a str, &'a str, Option<&'a str>, [bool; 4]);
+
+fn variant(entry: &TimeTableEntry) -> Variant<'_> {
+    (
+        &entry.building,
+        &entry.room,
+        entry.details.as_deref(),
+        [
+            entry.status.cancelled,
+            entry.status.moved,
+            entry.status.online_only,
+            entry.status.substitute,
+        ],
+    )
+}
+
+/// Groups entries into weekly series.
+///
+/// Occurrences of a class on the same weekday and local time form a series, with an interval of
+/// the greatest common number of weeks between them. Weeks of the rule without an occurrence
+/// become `exdates`, and occurrences in another room or with another status become `overrides`
+/// of the most common one. Series are sorted by their first occurrence.
+pub fn detect_series(entries: Vec<TimeTableEntry>) -> Vec<Series> {
+    let mut classes: BTreeMap<SeriesKey, Vec<TimeTableEntry>> = BTreeMap::new();
+    for entry in entries {
+        classes
+            .entry(SeriesKey::of(&entry))
+            .or_default()
+            .push(entry);
+    }
+    let mut series: Vec<Series> = classes
+        .into_values()
+        .map(|mut occurrences| {
+            occurrences.sort_by_key(|entry| entry.datetime_beginning);
+            occurrences.dedup_by_key(|entry| local_date(entry.datetime_beginning));
+            to_series(occurrences)
+        })
+        .collect();
+    series.sort_by_key(|series| series.datetime_beginning);
+    series
+}
+
+/// Builds a series out of occurrences sorted by date, one per day
+fn to_series(occurrences: Vec<TimeTableEntry>) -> Series {
+    let first = &occurrences[0];
+    let first_date = local_date(first.datetime_beginning);
+    let weeks: Vec<i64> = occurrences
+        .iter()
+        .map(|entry| (local_date(entry.datetime_beginning) - first_date).num_weeks())
+        .collect();
+    let interval = weeks
+        .iter()
+        .fold(0, |interval, week| gcd(interval, *week))
+        .max(1);
+    let count = weeks[weeks.len() - 1] / interval + 1;
+
+    let time_beginning = first.datetime_beginning.with_timezone(&Warsaw).time();
+    let exdates = (0..count)
+        .map(|slot| slot * interval)
+        .filter(|week| !weeks.contains(week))
+        .filter_map(|week| {
+            let date = first_date + Duration::weeks(week);
+            Warsaw
+                .from_local_datetime(&date.and_time(time_beginning))
+                .earliest()
+                .map(|datetime| datetime.with_timezone(&Utc))
+        })
+        .collect();
+
+    let mut variants: BTreeMap<Variant, usize> = BTreeMap::new();
+    for entry in &occurrences {
+        *variants.entry(variant(entry)).or_default() += 1;
+    }
+    let common = variants
+        .iter()
+        .max_by_key(|(_, count)| **count)
+        .map(|(variant, _)| *variant)
+        .expect("Series has at least one occurrence");
+    let entry = occurrences
+        .iter()
+        .find(|entry| variant(entry) == common)
+        .expect("Common variant comes from an occurrence")
+        .clone();
+    let overrides = occurrences
+        .iter()
+        .filter(|entry| variant(entry) != common)
+        .cloned()
+        .collect();
+
+    Series {
+        id: hash_id(&["series", &first.id]),
+        datetime_beginning: first.datetime_beginning,
+        datetime_ending: first.datetime_ending,
+        rrule: format!("FREQ=WEEKLY;INTERVAL={interval};COUNT={count}"),
+        exdates,
+        overrides,
+        entry,
+    }
+}
+
+fn local_date(datetime: DateTime<Utc>) -> NaiveDate {
+    datetime.with_timezone(&Warsaw).date_naive()
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{mock_entries, mock_entry_at};
+
+    fn first_beginning() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 11, 6, 7, 30, 0).unwrap()
+    }
+
+    #[test]
+    fn missing_week_becomes_exdate() {
+        let mut entries = mock_entries(first_beginning(), 5);
+        let skipped = entries.remove(2);
+        let series = detect_series(entries);
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0].rrule(), "FREQ=WEEKLY;INTERVAL=1;COUNT=5");
+        assert_eq!(series[0].exdates(), [skipped.datetime_beginning]);
+        assert!(series[0].overrides().is_empty());
+    }
+
+    #[test]
+    fn detects_interval_of_weeks() {
+        let entries = mock_entries(first_beginning(), 5)
+            .into_iter()
+            .step_by(2)
+            .collect();
+        let series = detect_series(entries);
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0].rrule(), "FREQ=WEEKLY;INTERVAL=2;COUNT=3");
+        assert!(series[0].exdates().is_empty());
+    }
+
+    #[test]
+    fn other_room_becomes_override() {
+        let mut entries = mock_entries(first_beginning(), 3);
+        entries[1].room = "A/152".to_string();
+        let series = detect_series(entries);
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0].entry().get_room(), "B/227");
+        assert_eq!(series[0].overrides().len(), 1);
+        assert_eq!(series[0].overrides()[0].get_room(), "A/152");
+    }
+
+    #[test]
+    fn other_time_is_another_series() {
+        let entries = vec![
+            mock_entry_at(first_beginning()),
+            mock_entry_at(first_beginning() + Duration::days(7) + Duration::hours(2)),
+        ];
+        assert_eq!(detect_series(entries).len(), 2);
+    }
+}
@@ -0,0 +1,24 @@
+#![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
+
+use kuchiki::traits::TendrilSink;
+
+use crate::{error::ParseError, timetable::TimeTableEntry};
+
+/// Upstream format a single entry can be read from.
+///
+/// Every format ends up in the same `TimeTableEntry`, so fixes to the model only have to be made once.
+pub trait TimetableSource {
+    /// Parses one entry out of a raw response of the format
+    fn parse_entry(&self, input: &str) -> Result<TimeTableEntry, ParseError>;
+}
+
+/// HTML of the `RadToolTipManager1RTMPanel`, as rendered in the browser
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TooltipHtml;
+
+impl TimetableSource for TooltipHtml {
+    fn parse_entry(&self, input: &str) -> Result<TimeTableEntry, ParseError> {
+        let tooltip_node = kuchiki::parse_html().from_utf8().one(input.as_bytes());
+        tooltip_node.try_into()
+    }
+}
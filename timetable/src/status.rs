@@ -0,0 +1,143 @@
+#![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
+
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+
+use crate::text::fold;
+
+const CANCELLED_MARKERS: [&str; 4] = ["odwolan", "anulowan", "nie odbedzie", "nie odbeda"];
+const MOVED_MARKERS: [&str; 6] = [
+    "przeniesion",
+    "przesunie",
+    "zmiana terminu",
+    "zmiana sali",
+    "nowy termin",
+    "odrabian",
+];
+const SUBSTITUTE_MARKERS: [&str; 2] = ["zastepstw", "zastepuje"];
+/// Whole words rather than stems, as subjects like "Technologie internetowe" or "Systemy
+/// wirtualne" and teams of students mention them too
+const ONLINE_MARKERS: [&str; 9] = [
+    "online",
+    "on-line",
+    "zdalnie",
+    "e-learning",
+    "ms teams",
+    "microsoft teams",
+    "na teams",
+    "w teams",
+    "przez teams",
+];
+
+/// Changes announced in the free text of an entry, `#ctl06_OpisLabel` or the title
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, Hash, Object)]
+pub struct EntryStatus {
+    /// Class doesn't take place
+    pub(crate) cancelled: bool,
+    /// Class was moved to another date, time or room
+    pub(crate) moved: bool,
+    /// Class takes place only online
+    pub(crate) online_only: bool,
+    /// Class is led by a substitute tutor
+    pub(crate) substitute: bool,
+}
+
+impl EntryStatus {
+    pub fn detect(title: Option<&str>, details: Option<&str>) -> Self {
+        let text = fold(
+            &[title, details]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(" "),
+        );
+        let has_marker = |markers: &[&str]| markers.iter().any(|marker| text.contains(marker));
+        let words: Vec<&str> = text
+            .split(|c: char| !c.is_alphanumeric() && c != '-')
+            .filter(|word| !word.is_empty())
+            .collect();
+        let has_words = |markers: &[&str]| {
+            markers.iter().any(|marker| {
+                let marker: Vec<&str> = marker.split(' ').collect();
+                words.windows(marker.len()).any(|window| window == marker)
+            })
+        };
+        Self {
+            cancelled: has_marker(&CANCELLED_MARKERS),
+            moved: has_marker(&MOVED_MARKERS),
+            online_only: has_words(&ONLINE_MARKERS),
+            substitute: has_marker(&SUBSTITUTE_MARKERS),
+        }
+    }
+    pub fn cancelled(&self) -> bool {
+        self.cancelled
+    }
+    pub fn moved(&self) -> bool {
+        self.moved
+    }
+    pub fn online_only(&self) -> bool {
+        self.online_only
+    }
+    pub fn substitute(&self) -> bool {
+        self.substitute
+    }
+    /// Whether any change was announced
+    pub fn is_changed(&self) -> bool {
+        self.cancelled || self.moved || self.online_only || self.substitute
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn online(details: &str) -> bool {
+        EntryStatus::detect(None, Some(details)).online_only()
+    }
+
+    #[test]
+    fn detects_online_markers() {
+        for details in [
+            "Zajęcia odbędą się online",
+            "Wykład on-line",
+            "Zajęcia prowadzone zdalnie.",
+            "Materiały na platformie e-learning",
+            "Spotkanie na MS Teams",
+            "Link w Microsoft Teams",
+            "Zajęcia na Teams",
+            "Konsultacje w Teams",
+            "Przez Teams, link w mailu",
+        ] {
+            assert!(online(details), "{details}");
+        }
+        assert!(EntryStatus::detect(Some("Wykład ONLINE"), None).online_only());
+    }
+
+    #[test]
+    fn ignores_words_containing_markers() {
+        for details in [
+            "Technologie internetowe",
+            "Systemy wirtualne",
+            "Projekt w zespołach, teams po 3 osoby",
+            "Praca w zespołach (teams)",
+            "Onlineshop - projekt",
+        ] {
+            assert!(!online(details), "{details}");
+        }
+        assert!(!EntryStatus::detect(Some("Technologie internetowe"), None).online_only());
+    }
+
+    #[test]
+    fn detects_other_changes() {
+        let status = EntryStatus::detect(
+            Some("Zajęcia odwołane"),
+            Some("Zastępstwo, przeniesione do sali A/152"),
+        );
+        assert!(status.cancelled());
+        assert!(status.moved());
+        assert!(status.substitute());
+        assert!(!status.online_only());
+        assert!(status.is_changed());
+        assert!(!EntryStatus::detect(Some("Wykład"), None).is_changed());
+    }
+}
@@ -0,0 +1,107 @@
+#![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
+
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+
+/// Count of students parsed from `#ctl06_LiczbaStudentowLabel`, e.g. `115 115 ITN`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Object)]
+pub struct StudentsCount {
+    /// Number of enrolled students
+    pub(crate) enrolled: Option<u32>,
+    /// Limit of students
+    pub(crate) limit: Option<u32>,
+    /// Code of study program
+    pub(crate) program: Option<String>,
+    /// Count as shown in the tooltip
+    pub(crate) raw: String,
+}
+
+impl StudentsCount {
+    pub fn parse(raw: &str) -> Self {
+        let mut tokens = raw.split_whitespace().peekable();
+        let enrolled = tokens.peek().and_then(|token| token.parse().ok());
+        if enrolled.is_some() {
+            tokens.next();
+        }
+        let limit = tokens.peek().and_then(|token| token.parse().ok());
+        if limit.is_some() {
+            tokens.next();
+        }
+        let program = tokens.collect::<Vec<_>>().join(" ");
+        Self {
+            enrolled,
+            limit,
+            program: if program.is_empty() {
+                None
+            } else {
+                Some(program)
+            },
+            raw: raw.to_string(),
+        }
+    }
+    pub fn enrolled(&self) -> Option<u32> {
+        self.enrolled
+    }
+    pub fn limit(&self) -> Option<u32> {
+        self.limit
+    }
+    pub fn program(&self) -> Option<&str> {
+        self.program.as_deref()
+    }
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_enrolled_limit_and_program() {
+        let count = StudentsCount::parse("115 120 ITN");
+        assert_eq!(count.enrolled(), Some(115));
+        assert_eq!(count.limit(), Some(120));
+        assert_eq!(count.program(), Some("ITN"));
+        assert_eq!(count.raw(), "115 120 ITN");
+    }
+
+    #[test]
+    fn parses_plain_number() {
+        let count = StudentsCount::parse(" 42 ");
+        assert_eq!(count.enrolled(), Some(42));
+        assert_eq!(count.limit(), None);
+        assert_eq!(count.program(), None);
+        assert_eq!(count.raw(), " 42 ");
+    }
+
+    #[test]
+    fn parses_count_with_limit_only() {
+        let count = StudentsCount::parse("28 30");
+        assert_eq!(count.enrolled(), Some(28));
+        assert_eq!(count.limit(), Some(30));
+        assert_eq!(count.program(), None);
+    }
+
+    #[test]
+    fn empty_input_has_nothing() {
+        for raw in ["", "   "] {
+            let count = StudentsCount::parse(raw);
+            assert_eq!(count.enrolled(), None);
+            assert_eq!(count.limit(), None);
+            assert_eq!(count.program(), None);
+        }
+    }
+
+    #[test]
+    fn junk_is_kept_as_program() {
+        let count = StudentsCount::parse("brak danych");
+        assert_eq!(count.enrolled(), None);
+        assert_eq!(count.limit(), None);
+        assert_eq!(count.program(), Some("brak danych"));
+
+        let count = StudentsCount::parse("-5 ITN 20");
+        assert_eq!(count.enrolled(), None);
+        assert_eq!(count.program(), Some("-5 ITN 20"));
+    }
+}
@@ -0,0 +1,19 @@
+#![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
+
+/// Lowercase text without Polish diacritics, as the plan uses both spellings
+pub(crate) fn fold(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'ą' => 'a',
+            'ć' => 'c',
+            'ę' => 'e',
+            'ł' => 'l',
+            'ń' => 'n',
+            'ó' => 'o',
+            'ś' => 's',
+            'ź' | 'ż' => 'z',
+            c => c,
+        })
+        .collect()
+}
@@ -1,63 +1,159 @@
 #![deny(clippy::perf, clippy::complexity, clippy::style, unused_imports)]
 
-use std::{error::Error, fmt::Display};
-
-use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
-use chrono_tz::Europe::Warsaw;
+use chrono::{
+    DateTime, Duration, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, Offset,
+    TimeZone, Utc,
+};
+use chrono_tz::{Europe::Warsaw, Tz};
 use kuchiki::NodeRef;
-use poem_openapi::Object;
+use poem_openapi::{Enum, Object};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, Clone, Object)]
+use crate::{
+    builder::TimeTableEntryBuilder, error::ParseError, group::GroupCode, kind::EntryKind,
+    location::Location, merge::EntryPart, migration::SCHEMA_VERSION, person::Person,
+    status::EntryStatus, students::StudentsCount,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Object)]
 #[oai]
 pub struct TimeTableEntry {
+    /// Stable id of entry, derived from the plan cell or from the entry itself
+    #[serde(rename = "_id")]
+    pub(crate) id: String,
+    /// Id of the plan cell the entry was read from
+    #[serde(default)]
+    pub(crate) source_id: Option<String>,
     /// Title of entry
     pub(crate) title: Option<String>,
     /// Persons
     pub(crate) persons: Vec<String>,
+    /// Persons split into names and titles
+    #[serde(default)]
+    pub(crate) tutors: Vec<Person>,
     /// Details of entry
     pub(crate) details: Option<String>,
+    /// Changes announced in the title or details
+    #[serde(default)]
+    pub(crate) status: EntryStatus,
     /// Type of entry
     pub(crate) type_of: String,
+    /// Kind of entry, in English
+    #[serde(default)]
+    pub(crate) kind: EntryKind,
     /// Subjects
     pub(crate) subjects: Vec<String>,
     /// Subjects codes
     pub(crate) subject_codes: Vec<String>,
     /// Groups
     pub(crate) groups: Option<Vec<String>>,
+    /// Groups split into their parts
+    #[serde(default)]
+    pub(crate) group_codes: Vec<GroupCode>,
     /// Count of students
-    pub(crate) students_count: Option<String>,
+    #[serde(default)]
+    pub(crate) students_count: Option<StudentsCount>,
     /// Building
     pub(crate) building: String,
     /// Room
     pub(crate) room: String,
+    /// Building and room split into their parts
+    #[serde(default)]
+    pub(crate) location: Location,
     /// Date and time of beginning
     #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
     pub(crate) datetime_beginning: DateTime<Utc>,
     /// Date and time of ending
     #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
     pub(crate) datetime_ending: DateTime<Utc>,
+    /// Set when the local time of entry fell into a DST transition and had to be adjusted
+    #[serde(default)]
+    pub(crate) dst_adjustment: Option<DstAdjustment>,
+    /// Entries this one was merged from, empty unless merged
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) parts: Vec<EntryPart>,
+    /// Beginning and ending in the time zone asked for, never stored
+    #[serde(skip)]
+    #[oai(skip_serializing_if_is_none)]
+    pub(crate) local_time: Option<LocalTime>,
+    /// Version of the stored document layout, see `migration::SCHEMA_VERSION`
+    #[serde(default)]
+    #[oai(skip)]
+    pub(crate) schema_version: i32,
+}
+
+/// Beginning and ending of entry in a time zone of choice
+#[derive(Debug, Clone, PartialEq, Eq, Object)]
+pub struct LocalTime {
+    /// IANA name of the time zone, e.g. `Europe/Warsaw`
+    pub(crate) timezone: String,
+    /// Local date and time of beginning, with the offset from UTC
+    pub(crate) datetime_beginning: DateTime<FixedOffset>,
+    /// Local date and time of ending, with the offset from UTC
+    pub(crate) datetime_ending: DateTime<FixedOffset>,
+}
+
+/// How a local time falling into a DST transition in Europe/Warsaw was resolved
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Enum)]
+#[serde(rename_all = "snake_case")]
+#[oai(rename_all = "snake_case")]
+pub enum DstAdjustment {
+    /// Time happened twice (October fall-back), the earlier (summer time) offset was used
+    AmbiguousEarliest,
+    /// Time happened twice (October fall-back), the later (winter time) offset was used
+    AmbiguousLatest,
+    /// Time didn't happen at all (March spring-forward), it was shifted forward by the gap,
+    /// the entry keeps its duration
+    ShiftedForward,
 }
 
 impl TryFrom<NodeRef> for TimeTableEntry {
-    type Error = Box<dyn Error>;
+    type Error = ParseError;
 
     fn try_from(dom: NodeRef) -> Result<Self, Self::Error> {
-        let date = get_data(&dom, "#ctl06_DataZajecLabel", "date")?;
-        let (datetime_beginning, datetime_ending) = extract_date_from_string(&dom, date)?;
-        let result = TimeTableEntry {
-            title: get_data_option(&dom, "#ctl06_TytulRezerwacjiLabel"),
-            persons: get_multiple_data(
-                &dom,
-                "#ctl06_OsobaRezerwujacaLabel, #ctl06_DydaktycyLabel",
-                "persons",
-            )?,
-            details: get_data_option(&dom, "#ctl06_OpisLabel"),
-            type_of: get_data(
+        let date = get_data(&dom, DATE_SELECTOR, "date")?;
+        let (datetime_beginning, datetime_ending, dst_adjustment) =
+            extract_date_from_string(&dom, date)?;
+        let groups = {
+            let groups = get_multiple_data(
                 &dom,
-                "#ctl06_TypRezerwacjiLabel, #ctl06_TypZajecLabel",
-                "type_of",
-            )?,
+                "#ctl06_GrupyStudenckieLabel, #ctl06_GrupyLabel",
+                "groups",
+            )?;
+            if groups.iter().all(|group| group == "---") {
+                None
+            } else {
+                Some(groups)
+            }
+        };
+        let persons = get_multiple_data(
+            &dom,
+            "#ctl06_OsobaRezerwujacaLabel, #ctl06_DydaktycyLabel",
+            "persons",
+        )?;
+        let type_of = get_data(
+            &dom,
+            "#ctl06_TypRezerwacjiLabel, #ctl06_TypZajecLabel",
+            "type_of",
+        )?;
+        let is_reservation = dom.select_first("#ctl06_TypRezerwacjiLabel").is_ok();
+        let building = get_data(&dom, "#ctl06_BudynekLabel", "building")?;
+        let room = get_data(&dom, "#ctl06_SalaLabel", "room")?;
+        let title = get_data_option(&dom, "#ctl06_TytulRezerwacjiLabel");
+        let details = get_data_option(&dom, "#ctl06_OpisLabel");
+        let result = TimeTableEntry {
+            id: String::new(),
+            source_id: None,
+            status: EntryStatus::detect(title.as_deref(), details.as_deref()),
+            title,
+            tutors: persons
+                .iter()
+                .filter_map(|person| Person::parse(person))
+                .collect(),
+            persons,
+            details,
+            kind: EntryKind::from_label(&type_of, is_reservation),
+            type_of,
             subjects: get_multiple_data(
                 &dom,
                 "#ctl06_NazwyPrzedmiotowLabel, #ctl06_NazwaPrzedmiotyLabel",
@@ -68,82 +164,244 @@ impl TryFrom<NodeRef> for TimeTableEntry {
                 "#ctl06_KodyPrzedmiotowLabel, #ctl06_KodPrzedmiotuLabel",
                 "subject_codes",
             )?,
-            groups: {
-                let groups = get_multiple_data(
-                    &dom,
-                    "#ctl06_GrupyStudenckieLabel, #ctl06_GrupyLabel",
-                    "groups",
-                )?;
-                if groups.iter().all(|group| group == "---") {
-                    None
-                } else {
-                    Some(groups)
-                }
-            },
-            students_count: get_data_option(&dom, "#ctl06_LiczbaStudentowLabel"),
-            building: get_data(&dom, "#ctl06_BudynekLabel", "building")?,
-            room: get_data(&dom, "#ctl06_SalaLabel", "room")?,
+            group_codes: parse_group_codes(groups.as_deref()),
+            groups,
+            students_count: get_data_option(&dom, "#ctl06_LiczbaStudentowLabel")
+                .map(|students_count| StudentsCount::parse(&students_count)),
+            location: Location::parse(&building, &room),
+            building,
+            room,
             datetime_beginning,
             datetime_ending,
+            dst_adjustment,
+            parts: vec![],
+            local_time: None,
+            schema_version: SCHEMA_VERSION,
         };
-        Ok(result)
+        Ok(result.with_content_id())
     }
 }
 
-fn extract_date_from_string(
-    dom: &NodeRef,
-    date: String,
-) -> Result<(DateTime<Utc>, DateTime<Utc>), Box<dyn Error>> {
-    let hour_beginning = get_data(dom, "#ctl06_GodzRozpLabel", "hour_beginning")?;
-    let hour_ending = get_data(dom, "#ctl06_GodzZakonLabel", "hour_ending")?;
-    let beginning_str = format!("{} {}", date, hour_beginning);
-    let ending_str = format!("{} {}", date, hour_ending);
-    let beginning_naive = NaiveDateTime::parse_from_str(&beginning_str, "%d.%m.%Y %T")?;
-    let ending_naive = NaiveDateTime::parse_from_str(&ending_str, "%d.%m.%Y %T")?;
-    let datetime_beginning = Warsaw
-        .from_local_datetime(&beginning_naive)
-        .single()
-        .expect("Beginning time parsing failed!")
-        .with_timezone(&Utc);
-    let datetime_ending = Warsaw
-        .from_local_datetime(&ending_naive)
-        .single()
-        .expect("Ending time parsing failed!")
-        .with_timezone(&Utc);
-    Ok((datetime_beginning, datetime_ending))
+const DATE_SELECTOR: &str = "#ctl06_DataZajecLabel";
+const HOUR_BEGINNING_SELECTOR: &str = "#ctl06_GodzRozpLabel";
+const HOUR_ENDING_SELECTOR: &str = "#ctl06_GodzZakonLabel";
+
+/// Beginning, ending and DST adjustment of entry
+type EntryTimes = (DateTime<Utc>, DateTime<Utc>, Option<DstAdjustment>);
+
+fn extract_date_from_string(dom: &NodeRef, date: String) -> Result<EntryTimes, ParseError> {
+    let hour_beginning = get_data(dom, HOUR_BEGINNING_SELECTOR, "hour_beginning")?;
+    let hour_ending = get_data(dom, HOUR_ENDING_SELECTOR, "hour_ending")?;
+    let date_naive =
+        NaiveDate::parse_from_str(&date, "%d.%m.%Y").map_err(|_| ParseError::BadDateFormat {
+            selector: DATE_SELECTOR,
+            raw: date.clone(),
+        })?;
+    let beginning = date_naive.and_time(parse_time(HOUR_BEGINNING_SELECTOR, &hour_beginning)?);
+    let ending = date_naive.and_time(parse_time(HOUR_ENDING_SELECTOR, &hour_ending)?);
+    let (datetime_beginning, beginning_adjustment) =
+        to_utc(beginning, HOUR_BEGINNING_SELECTOR, &hour_beginning, None)?;
+    let (datetime_ending, ending_adjustment) = match beginning_adjustment {
+        // Ending keeps the scheduled duration, so it moves together with a beginning shifted out of the gap
+        Some(DstAdjustment::ShiftedForward) => (datetime_beginning + (ending - beginning), None),
+        _ => to_utc(
+            ending,
+            HOUR_ENDING_SELECTOR,
+            &hour_ending,
+            Some(datetime_beginning),
+        )?,
+    };
+    if datetime_ending <= datetime_beginning {
+        return Err(ParseError::EndBeforeStart {
+            selector: HOUR_ENDING_SELECTOR,
+            raw: hour_ending,
+            beginning: hour_beginning,
+        });
+    }
+    Ok((
+        datetime_beginning,
+        datetime_ending,
+        beginning_adjustment.or(ending_adjustment),
+    ))
+}
+
+fn parse_time(selector: &'static str, hour: &str) -> Result<NaiveTime, ParseError> {
+    NaiveTime::parse_from_str(hour, "%T").map_err(|_| ParseError::BadTimeFormat {
+        selector,
+        raw: hour.to_string(),
+    })
+}
+
+/// Converts local time in Europe/Warsaw to UTC.
+///
+/// Ambiguous times resolve to the earlier offset, unless that would put them at or before `after`,
+/// in which case the later offset is used. Nonexistent times are shifted forward by an hour.
+fn to_utc(
+    naive: NaiveDateTime,
+    selector: &'static str,
+    hour: &str,
+    after: Option<DateTime<Utc>>,
+) -> Result<(DateTime<Utc>, Option<DstAdjustment>), ParseError> {
+    let (datetime, adjustment) = match Warsaw.from_local_datetime(&naive) {
+        LocalResult::Single(datetime) => (datetime, None),
+        LocalResult::Ambiguous(earliest, latest) => match after {
+            Some(after) if earliest <= after => (latest, Some(DstAdjustment::AmbiguousLatest)),
+            _ => (earliest, Some(DstAdjustment::AmbiguousEarliest)),
+        },
+        LocalResult::None => match Warsaw.from_local_datetime(&(naive + Duration::hours(1))) {
+            LocalResult::Single(datetime) => (datetime, Some(DstAdjustment::ShiftedForward)),
+            _ => {
+                return Err(ParseError::AmbiguousLocalTime {
+                    selector,
+                    raw: hour.to_string(),
+                })
+            }
+        },
+    };
+    Ok((datetime.with_timezone(&Utc), adjustment))
+}
+
+pub(crate) fn parse_group_codes(groups: Option<&[String]>) -> Vec<GroupCode> {
+    groups
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|group| GroupCode::parse(group))
+        .collect()
 }
 
 impl TimeTableEntry {
+    pub fn builder() -> TimeTableEntryBuilder {
+        TimeTableEntryBuilder::new()
+    }
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+    pub fn get_source_id(&self) -> Option<&str> {
+        self.source_id.as_deref()
+    }
+    pub fn get_title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+    pub fn get_persons(&self) -> &[String] {
+        &self.persons
+    }
+    pub fn get_tutors(&self) -> &[Person] {
+        &self.tutors
+    }
+    pub fn get_details(&self) -> Option<&str> {
+        self.details.as_deref()
+    }
+    pub fn get_status(&self) -> &EntryStatus {
+        &self.status
+    }
+    pub fn get_type_of(&self) -> &str {
+        &self.type_of
+    }
+    pub fn get_kind(&self) -> &EntryKind {
+        &self.kind
+    }
+    pub fn get_subjects(&self) -> &[String] {
+        &self.subjects
+    }
+    pub fn get_subject_codes(&self) -> &[String] {
+        &self.subject_codes
+    }
+    pub fn get_groups(&self) -> Option<&[String]> {
+        self.groups.as_deref()
+    }
+    pub fn get_group_codes(&self) -> &[GroupCode] {
+        &self.group_codes
+    }
+    pub fn get_students_count(&self) -> Option<&StudentsCount> {
+        self.students_count.as_ref()
+    }
+    pub fn get_building(&self) -> &str {
+        &self.building
+    }
+    pub fn get_room(&self) -> &str {
+        &self.room
+    }
+    pub fn get_location(&self) -> &Location {
+        &self.location
+    }
     pub fn get_datetime_beginning(&self) -> DateTime<Utc> {
         self.datetime_beginning
     }
+    pub fn get_datetime_ending(&self) -> DateTime<Utc> {
+        self.datetime_ending
+    }
+    pub fn get_dst_adjustment(&self) -> Option<DstAdjustment> {
+        self.dst_adjustment
+    }
+    pub fn get_parts(&self) -> &[EntryPart] {
+        &self.parts
+    }
+    pub fn get_local_time(&self) -> Option<&LocalTime> {
+        self.local_time.as_ref()
+    }
+    pub fn get_schema_version(&self) -> i32 {
+        self.schema_version
+    }
+    /// Adds beginning and ending in the time zone, for clients not in Europe/Warsaw
+    pub fn with_local_time(mut self, timezone: Tz) -> Self {
+        self.local_time = Some(LocalTime {
+            timezone: timezone.name().to_string(),
+            datetime_beginning: with_offset(self.datetime_beginning, timezone),
+            datetime_ending: with_offset(self.datetime_ending, timezone),
+        });
+        self
+    }
+    /// Ties the entry to the plan cell it was read from, deriving its id from the cell
+    pub fn with_source_id(mut self, cell_id: &str) -> Self {
+        let date = self
+            .datetime_beginning
+            .with_timezone(&Warsaw)
+            .format("%Y-%m-%d")
+            .to_string();
+        self.id = hash_id(&["cell", cell_id, &date]);
+        self.source_id = Some(cell_id.to_string());
+        self
+    }
+    /// Derives id of the entry from its date, time, room and subject codes
+    pub(crate) fn with_content_id(mut self) -> Self {
+        let mut subject_codes = self.subject_codes.clone();
+        subject_codes.sort();
+        self.id = hash_id(&[
+            "content",
+            &self.datetime_beginning.timestamp().to_string(),
+            &self.datetime_ending.timestamp().to_string(),
+            &self.building,
+            &self.room,
+            &subject_codes.join(","),
+        ]);
+        self
+    }
 }
-fn get_mock_entry() -> TimeTableEntry {
-    // Sample entry
-    TimeTableEntry {
-        title: Some("Ostatni wykład".to_string()),
-        persons: vec![
-            "Niezgoda Adam".to_string(),
-            "Tomaszewski Michał".to_string(),
-        ],
-        details: Some("Podsumowanie semestru".to_string()),
-        type_of: "Wykład".to_string(),
-        subjects: vec![
-            "Systemy operacyjne".to_string(),
-            "Programowanie obiektowe i GUI".to_string(),
-        ],
-        subject_codes: vec!["SOP".to_string(), "GUI".to_string()],
-        groups: Some(vec![
-            "WIs I.2 - 46c".to_string(),
-            "WIS I.2 - 23c".to_string(),
-        ]),
-        students_count: Some("115 115 ITN".to_string()),
-        building: "B2020".to_string(),
-        room: "B/227".to_string(),
-        datetime_beginning: Utc::now(),
-        datetime_ending: Utc::now() + Duration::hours(2),
+
+/// Local time in the time zone, keeping only its offset from UTC
+fn with_offset(datetime: DateTime<Utc>, timezone: Tz) -> DateTime<FixedOffset> {
+    let offset = timezone
+        .offset_from_utc_datetime(&datetime.naive_utc())
+        .fix();
+    datetime.with_timezone(&offset)
+}
+
+/// 64-bit FNV-1a of the parts, stable between builds unlike `DefaultHasher`
+pub(crate) fn hash_id(parts: &[&str]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for (index, part) in parts.iter().enumerate() {
+        if index > 0 {
+            hash ^= 0x1f;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        for byte in part.bytes() {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
     }
+    format!("{:016x}", hash)
 }
+
 fn get_data_option(dom: &NodeRef, selector: &'static str) -> Option<String> {
     if let Ok(dom) = dom.select_first(selector) {
         Some(dom.text_contents().trim().to_string())
@@ -159,7 +417,10 @@ fn get_data(
 ) -> Result<String, ParseError> {
     let date = dom
         .select_first(selector)
-        .map_err(|_| ParseError { cause })?
+        .map_err(|_| ParseError::MissingSelector {
+            selector,
+            field: cause,
+        })?
         .text_contents()
         .trim()
         .to_string();
@@ -173,28 +434,204 @@ fn get_multiple_data(
 ) -> Result<Vec<String>, ParseError> {
     Ok(dom
         .select_first(selectors)
-        .map_err(|_| ParseError { cause })?
+        .map_err(|_| ParseError::MissingSelector {
+            selector: selectors,
+            field: cause,
+        })?
         .text_contents()
         .trim()
         .split_terminator(',')
-        .into_iter()
         .map(|a| a.trim().to_string())
         .collect())
 }
 
-#[derive(Debug)]
-struct ParseError {
-    cause: &'static str,
-}
+#[cfg(test)]
+mod tests {
+    use kuchiki::traits::TendrilSink;
+
+    use super::*;
+
+    /// Years with the days clocks go forward in March and back in October in Europe/Warsaw
+    const TRANSITIONS: [(i32, u32, u32); 5] = [
+        (2022, 27, 30),
+        (2023, 26, 29),
+        (2024, 31, 27),
+        (2025, 30, 26),
+        (2026, 29, 25),
+    ];
 
-impl Display for ParseError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        std::fmt::write(f, format_args!("Parse error: {}", self.cause))
+    fn at(year: i32, month: u32, day: u32, hour: &str) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_time(parse_time(HOUR_BEGINNING_SELECTOR, hour).unwrap())
     }
-}
 
-impl Error for ParseError {
-    fn cause(&self) -> Option<&dyn Error> {
-        None
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    fn entry_times(date: &str, beginning: &str, ending: &str) -> Result<EntryTimes, ParseError> {
+        let tooltip = format!(
+            "<span id=\"ctl06_GodzRozpLabel\">{beginning}</span>\
+             <span id=\"ctl06_GodzZakonLabel\">{ending}</span>"
+        );
+        let dom = kuchiki::parse_html().one(tooltip);
+        extract_date_from_string(&dom, date.to_string())
+    }
+
+    #[test]
+    fn ambiguous_hour_takes_summer_time_first() {
+        for (year, _, day) in TRANSITIONS {
+            assert_eq!(
+                to_utc(
+                    at(year, 10, day, "02:30:00"),
+                    HOUR_BEGINNING_SELECTOR,
+                    "02:30:00",
+                    None
+                ),
+                Ok((
+                    utc(year, 10, day, 0, 30),
+                    Some(DstAdjustment::AmbiguousEarliest)
+                )),
+                "{year}"
+            );
+            assert_eq!(
+                to_utc(
+                    at(year, 10, day, "02:30:00"),
+                    HOUR_ENDING_SELECTOR,
+                    "02:30:00",
+                    Some(utc(year, 10, day, 0, 45))
+                ),
+                Ok((
+                    utc(year, 10, day, 1, 30),
+                    Some(DstAdjustment::AmbiguousLatest)
+                )),
+                "{year}"
+            );
+        }
+    }
+
+    #[test]
+    fn ambiguous_ending_falls_after_beginning() {
+        for (year, _, day) in TRANSITIONS {
+            assert_eq!(
+                entry_times(&format!("{day}.10.{year}"), "02:45:00", "02:15:00"),
+                Ok((
+                    utc(year, 10, day, 0, 45),
+                    utc(year, 10, day, 1, 15),
+                    Some(DstAdjustment::AmbiguousEarliest)
+                )),
+                "{year}"
+            );
+        }
+    }
+
+    #[test]
+    fn hours_around_fall_back_are_unambiguous() {
+        for (year, _, day) in TRANSITIONS {
+            assert_eq!(
+                entry_times(&format!("{day}.10.{year}"), "01:30:00", "03:30:00"),
+                Ok((
+                    utc(year, 10, day - 1, 23, 30),
+                    utc(year, 10, day, 2, 30),
+                    None
+                )),
+                "{year}"
+            );
+        }
+    }
+
+    #[test]
+    fn gap_is_shifted_forward() {
+        for (year, day, _) in TRANSITIONS {
+            assert_eq!(
+                to_utc(
+                    at(year, 3, day, "02:30:00"),
+                    HOUR_BEGINNING_SELECTOR,
+                    "02:30:00",
+                    None
+                ),
+                Ok((
+                    utc(year, 3, day, 1, 30),
+                    Some(DstAdjustment::ShiftedForward)
+                )),
+                "{year}"
+            );
+        }
+    }
+
+    #[test]
+    fn entry_beginning_in_gap_keeps_duration() {
+        for (year, day, _) in TRANSITIONS {
+            let date = format!("{day}.03.{year}");
+            assert_eq!(
+                entry_times(&date, "02:30:00", "03:15:00"),
+                Ok((
+                    utc(year, 3, day, 1, 30),
+                    utc(year, 3, day, 2, 15),
+                    Some(DstAdjustment::ShiftedForward)
+                )),
+                "{year}"
+            );
+            assert_eq!(
+                entry_times(&date, "02:15:00", "02:45:00"),
+                Ok((
+                    utc(year, 3, day, 1, 15),
+                    utc(year, 3, day, 1, 45),
+                    Some(DstAdjustment::ShiftedForward)
+                )),
+                "{year}"
+            );
+        }
+    }
+
+    #[test]
+    fn entry_ending_in_gap_is_shifted() {
+        for (year, day, _) in TRANSITIONS {
+            assert_eq!(
+                entry_times(&format!("{day}.03.{year}"), "01:30:00", "02:30:00"),
+                Ok((
+                    utc(year, 3, day, 0, 30),
+                    utc(year, 3, day, 1, 30),
+                    Some(DstAdjustment::ShiftedForward)
+                )),
+                "{year}"
+            );
+        }
+    }
+
+    #[test]
+    fn day_before_transition_is_unaffected() {
+        for (year, march, october) in TRANSITIONS {
+            assert_eq!(
+                to_utc(
+                    at(year, 3, march - 1, "02:30:00"),
+                    HOUR_BEGINNING_SELECTOR,
+                    "02:30:00",
+                    None
+                ),
+                Ok((utc(year, 3, march - 1, 1, 30), None)),
+                "{year}"
+            );
+            assert_eq!(
+                to_utc(
+                    at(year, 10, october - 1, "02:30:00"),
+                    HOUR_BEGINNING_SELECTOR,
+                    "02:30:00",
+                    None
+                ),
+                Ok((utc(year, 10, october - 1, 0, 30), None)),
+                "{year}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_ending_before_beginning() {
+        assert!(matches!(
+            entry_times("16.10.2024", "10:00:00", "08:30:00"),
+            Err(ParseError::EndBeforeStart { .. })
+        ));
     }
 }